@@ -0,0 +1,124 @@
+//! 現在の盤面の解析結果
+//!
+//! 難易度などは盤面とルールだけで決まるため、盤面が変わったときに1度だけ求めて使い回す。
+
+use crate::game::{
+    Calculator, DIFFICULTY_SOLUTION_LIMIT, Difficulty, Expr, FirstStep, GameNumbers, RuleSet,
+    SolutionSpace,
+};
+use bevy::prelude::*;
+use std::sync::OnceLock;
+
+/// 盤面ごとに1度だけ求める解析結果
+#[derive(Resource)]
+pub struct BoardAnalysis {
+    /// 解析した盤面
+    numbers: GameNumbers,
+    /// 解析したときのルール
+    rules: RuleSet,
+    /// 盤面の解の空間
+    space: SolutionSpace,
+    /// 盤面の難易度（解けない盤面の場合は`None`）
+    difficulty: Option<Difficulty>,
+    /// ヒントに使う解の最初の1手（解けない盤面の場合は`None`）
+    first_step: Option<FirstStep>,
+    /// 本質的に異なる解（ギブアップしたときに初めて求める）
+    solutions: OnceLock<Vec<Expr>>,
+}
+
+impl BoardAnalysis {
+    /// 盤面を解析する
+    pub fn new(numbers: &GameNumbers, rules: &RuleSet) -> Self {
+        let space = SolutionSpace::new(numbers, rules);
+        Self {
+            numbers: numbers.clone(),
+            rules: rules.clone(),
+            difficulty: space.difficulty(DIFFICULTY_SOLUTION_LIMIT),
+            first_step: Calculator::first_step(numbers, rules),
+            solutions: OnceLock::new(),
+            space,
+        }
+    }
+
+    /// 指定した盤面とルールの解析結果か
+    pub fn is_for(&self, numbers: &GameNumbers, rules: &RuleSet) -> bool {
+        self.numbers == *numbers && self.rules == *rules
+    }
+
+    /// 盤面の難易度（解けない盤面の場合は`None`）
+    pub fn difficulty(&self) -> Option<&Difficulty> {
+        self.difficulty.as_ref()
+    }
+
+    /// 解の最初の1手（解けない盤面の場合は`None`）
+    pub fn first_step(&self) -> Option<&FirstStep> {
+        self.first_step.as_ref()
+    }
+
+    /// すべての本質的に異なる解
+    ///
+    /// 最初に呼ばれたときに求め、同じ盤面では結果を使い回す。
+    pub fn solutions(&self) -> &[Expr] {
+        self.solutions
+            .get_or_init(|| self.space.distinct_solutions(usize::MAX))
+    }
+}
+
+impl Default for BoardAnalysis {
+    /// 数字のない盤面の解析結果（最初の盤面が決まるまでの仮の値）
+    fn default() -> Self {
+        Self::new(&GameNumbers::from_digits(Vec::new()), &RuleSet::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solutions_are_not_capped() {
+        // テスト: 解の一覧は難易度の計算の上限に関係なくすべての解を含む
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4, 5]);
+        let rules = RuleSet::default().with_digit_count(5);
+        let analysis = BoardAnalysis::new(&numbers, &rules);
+        assert!(analysis.solutions().len() > DIFFICULTY_SOLUTION_LIMIT);
+        assert_eq!(
+            analysis.solutions(),
+            Calculator::distinct_solutions(&numbers, &rules)
+        );
+        assert_eq!(
+            analysis.difficulty().unwrap().distinct_solutions,
+            DIFFICULTY_SOLUTION_LIMIT
+        );
+    }
+
+    #[test]
+    fn test_analysis_matches_solver() {
+        // テスト: 解析結果の難易度はソルバーの結果と一致し、盤面とルールの組ごとに対応する
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        let rules = RuleSet::default();
+        assert_eq!(BoardAnalysis::default().difficulty(), None);
+
+        let analysis = BoardAnalysis::new(&numbers, &rules);
+        assert!(analysis.is_for(&numbers, &rules));
+        assert_eq!(
+            analysis.difficulty(),
+            Calculator::difficulty(&numbers, &rules).as_ref()
+        );
+        assert_eq!(
+            analysis.first_step(),
+            Calculator::first_step(&numbers, &rules).as_ref()
+        );
+        assert_eq!(
+            analysis.solutions(),
+            Calculator::distinct_solutions(&numbers, &rules)
+        );
+
+        let rules = rules.with_integer_intermediates(true);
+        assert!(!analysis.is_for(&numbers, &rules));
+        let analysis = BoardAnalysis::new(&numbers, &rules);
+        assert_eq!(analysis.difficulty(), None);
+        assert_eq!(analysis.first_step(), None);
+        assert!(analysis.solutions().is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! 解答の判定
+//!
+//! プレイヤーが提出した式が盤面の答えとして正しいかを調べ、間違っている場合はその理由を返す。
+
+use crate::game::{CalculationError, Expr, GameNumbers, Rational, RuleSet, RuleViolation};
+use std::fmt;
+
+/// 提出した式が答えとして認められない理由
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnswerError {
+    /// 式が完成していない
+    Incomplete,
+    /// 使っていないカードがある
+    NotAllNumbersUsed,
+    /// 計算できない（ゼロ除算など）
+    Calculation(CalculationError),
+    /// ルールに違反している
+    RuleViolation(RuleViolation),
+    /// 計算結果が目標の数と異なる
+    WrongResult(Rational),
+}
+
+impl AnswerError {
+    /// すべてのカードを使った完成した式が間違っていたか
+    ///
+    /// 入力途中の提出はライフを減らさず、間違った答えのみライフを減らす。
+    pub fn is_wrong_answer(&self) -> bool {
+        !matches!(
+            self,
+            AnswerError::Incomplete | AnswerError::NotAllNumbersUsed
+        )
+    }
+}
+
+impl fmt::Display for AnswerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnswerError::Incomplete => write!(f, "expression is incomplete"),
+            AnswerError::NotAllNumbersUsed => write!(f, "not all numbers used"),
+            AnswerError::Calculation(error) => write!(f, "{}", error),
+            AnswerError::RuleViolation(violation) => write!(f, "{}", violation),
+            AnswerError::WrongResult(value) => write!(f, "result is {}", value),
+        }
+    }
+}
+
+impl std::error::Error for AnswerError {}
+
+impl From<CalculationError> for AnswerError {
+    fn from(error: CalculationError) -> Self {
+        match error {
+            CalculationError::RuleViolation(violation) => AnswerError::RuleViolation(violation),
+            error => AnswerError::Calculation(error),
+        }
+    }
+}
+
+impl From<RuleViolation> for AnswerError {
+    fn from(violation: RuleViolation) -> Self {
+        AnswerError::RuleViolation(violation)
+    }
+}
+
+impl RuleSet {
+    /// 提出された式が盤面の答えとして正しいか判定する
+    ///
+    /// すべてのカードを1回ずつ使い、ルールを守り、目標の数とちょうど等しい場合のみ正解。
+    pub fn check_answer(&self, expr: &Expr, numbers: &GameNumbers) -> Result<(), AnswerError> {
+        if !expr.uses_all_numbers(numbers) {
+            return Err(AnswerError::NotAllNumbersUsed);
+        }
+        let value = expr.evaluate()?;
+        self.check(expr)?;
+        if value != self.target_value() {
+            return Err(AnswerError::WrongResult(value));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Operator, Token};
+
+    fn literal_tokens(numbers: &GameNumbers, layout: &str) -> Vec<Token> {
+        // 数字はカードの順番に、それ以外は演算子や括弧として並べる
+        let mut index = 0;
+        layout
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                'n' => {
+                    let token = Token::Literal {
+                        index,
+                        value: numbers.digits[index],
+                    };
+                    index += 1;
+                    token
+                }
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                c => Token::Operator(Operator::from_char(c).unwrap()),
+            })
+            .collect()
+    }
+
+    fn answer(digits: [u8; 4], layout: &str, rules: &RuleSet) -> Result<(), AnswerError> {
+        let numbers = GameNumbers::from_digits(digits);
+        let expr = Expr::from_tokens(&literal_tokens(&numbers, layout)).unwrap();
+        rules.check_answer(&expr, &numbers)
+    }
+
+    #[test]
+    fn test_correct_answer() {
+        // テスト: すべてのカードを使って10を作ると正解
+        let rules = RuleSet::default();
+        assert_eq!(answer([1, 2, 3, 4], "n + n + n + n", &rules), Ok(()));
+        assert_eq!(answer([8, 1, 1, 5], "n / (n - n / n)", &rules), Ok(()));
+    }
+
+    #[test]
+    fn test_failure_reasons() {
+        // テスト: 間違っている理由がわかる
+        let rules = RuleSet::default();
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let expr = Expr::from_tokens(&literal_tokens(&numbers, "n + n")).unwrap();
+        let error = rules.check_answer(&expr, &numbers).unwrap_err();
+        assert_eq!(error, AnswerError::NotAllNumbersUsed);
+        assert_eq!(error.to_string(), "not all numbers used");
+        assert!(!error.is_wrong_answer());
+
+        let error = answer([1, 2, 3, 4], "n + n + n * n", &rules).unwrap_err();
+        assert_eq!(error.to_string(), "result is 15");
+        assert!(error.is_wrong_answer());
+
+        let error = answer([1, 2, 2, 4], "n / (n - n) + n", &rules).unwrap_err();
+        assert_eq!(
+            error,
+            AnswerError::Calculation(CalculationError::DivisionByZero)
+        );
+        assert_eq!(error.to_string(), "division by zero");
+
+        let rules = rules.with_integer_intermediates(true);
+        let error = answer([8, 1, 1, 5], "n / (n - n / n)", &rules).unwrap_err();
+        assert!(matches!(
+            error,
+            AnswerError::RuleViolation(RuleViolation::FractionalIntermediate(_))
+        ));
+    }
+}
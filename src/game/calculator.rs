@@ -1,122 +1,353 @@
 //! 計算エンジンと数式検証
 
-use crate::game::GameNumbers;
+use crate::game::{
+    Expr, GameNumbers, Operator, OperatorSet, Rational, RuleSet, RuleViolation, SolutionSpace,
+    apply_operator,
+};
+use std::collections::{HashMap, HashSet};
+
+/// 探索中の数（値、数字を並べただけか、直前に単項演算を適用したか）
+type SearchItem = (Rational, bool, bool);
+
+/// 探索中の数とそれを作る式
+type SearchValue = (SearchItem, Expr);
+
+/// 列挙中の式（値、式、式中で使った演算子）
+type Enumerated = (Rational, Expr, OperatorSet);
 
 /// 計算結果を表す構造体
 #[derive(Debug, Clone, PartialEq)]
 pub struct CalculationResult {
-    pub result: f64,
-    pub is_valid: bool,
+    pub result: Rational,
     pub used_all_digits: bool,
 }
 
+/// 式の解析・評価で発生するエラー
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalculationError {
+    /// 式が空
+    EmptyExpression,
+    /// 解釈できない文字
+    UnknownToken(char),
+    /// 予期しない位置に現れたトークン
+    UnexpectedToken(String),
+    /// 式が途中で終わっている
+    UnexpectedEnd,
+    /// 括弧の対応が取れていない
+    UnbalancedParentheses,
+    /// ゼロ除算
+    DivisionByZero,
+    /// 計算途中でオーバーフローした
+    Overflow,
+    /// 同じ数字を与えられた回数より多く使用した
+    DigitReused(u8),
+    /// 与えられていない数字を使用した
+    DigitNotAvailable(u8),
+    /// 存在しないカード、または同じカードを2回選択した
+    InvalidCardSelection,
+    /// 演算子を適用できない値（整数でない指数、数字以外の連結など）
+    InvalidOperand(Operator),
+    /// ルールで認められない計算
+    RuleViolation(RuleViolation),
+}
+
+impl std::fmt::Display for CalculationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalculationError::EmptyExpression => write!(f, "empty expression"),
+            CalculationError::UnknownToken(c) => write!(f, "unknown token '{}'", c),
+            CalculationError::UnexpectedToken(token) => write!(f, "unexpected token '{}'", token),
+            CalculationError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            CalculationError::UnbalancedParentheses => write!(f, "unbalanced parentheses"),
+            CalculationError::DivisionByZero => write!(f, "division by zero"),
+            CalculationError::Overflow => write!(f, "arithmetic overflow"),
+            CalculationError::DigitReused(d) => write!(f, "digit {} used too many times", d),
+            CalculationError::DigitNotAvailable(d) => write!(f, "digit {} is not available", d),
+            CalculationError::InvalidCardSelection => write!(f, "invalid card selection"),
+            CalculationError::InvalidOperand(op) => write!(f, "invalid operand for '{}'", op),
+            CalculationError::RuleViolation(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+impl std::error::Error for CalculationError {}
+
+impl From<RuleViolation> for CalculationError {
+    fn from(violation: RuleViolation) -> Self {
+        CalculationError::RuleViolation(violation)
+    }
+}
+
 /// 計算エンジン
 pub struct Calculator;
 
 impl Calculator {
-    /// 4つの数字と演算で10を作れるかチェック
+    /// 数字と演算で10を作れるかチェック
     pub fn can_make_ten(numbers: &GameNumbers) -> bool {
-        // すべての可能な組み合わせを試す（ブルートフォース）
-        let digits = numbers.digits;
-
-        // 4つの数字の順列を生成
-        for a in 0..4 {
-            for b in 0..4 {
-                for c in 0..4 {
-                    for d in 0..4 {
-                        if a != b && a != c && a != d && b != c && b != d && c != d {
-                            let nums = [digits[a], digits[b], digits[c], digits[d]];
-
-                            // 3つの演算子の組み合わせを試す
-                            for op1 in &['+', '-', '*', '/'] {
-                                for op2 in &['+', '-', '*', '/'] {
-                                    for op3 in &['+', '-', '*', '/'] {
-                                        if Self::try_combination(nums, *op1, *op2, *op3) {
-                                            return true;
-                                        }
-                                    }
-                                }
-                            }
+        Self::can_make_target(numbers, &RuleSet::default())
+    }
+
+    /// 数字と演算でルールの目標の数を作れるかチェック
+    pub fn can_make_target(numbers: &GameNumbers, rules: &RuleSet) -> bool {
+        Self::find_solution(numbers, rules).is_some()
+    }
+
+    /// 目標の数を作る式を1つ探す（見つかった時点で探索を打ち切る）
+    pub fn find_solution(numbers: &GameNumbers, rules: &RuleSet) -> Option<Expr> {
+        let values: Vec<SearchValue> = numbers
+            .digits
+            .iter()
+            .enumerate()
+            .map(|(index, &d)| ((Rational::from(d), true, false), Expr::literal(index, d)))
+            .collect();
+        let mut failed = HashSet::new();
+        Self::search(values, OperatorSet::EMPTY, rules, &mut failed)
+    }
+
+    /// 2つの数を選んで1つにまとめる操作を再帰的に繰り返して目標の数を探す
+    ///
+    /// 任意の個数の数字に対して、すべての並びと括弧の付け方を試すことになる。
+    /// 単項演算は同じ数に続けて適用しない（`sqrt(sqrt(x))`のような無限の展開を防ぐ）。
+    /// 演算子を1回ずつ使うルールでは`used`に使った演算子を記録する。
+    /// 一度失敗した数の組み合わせは`failed`に記録して再探索しない。
+    fn search(
+        mut values: Vec<SearchValue>,
+        used: OperatorSet,
+        rules: &RuleSet,
+        failed: &mut HashSet<(Vec<SearchItem>, OperatorSet)>,
+    ) -> Option<Expr> {
+        let allowed = rules.allowed_operators();
+        let once = rules.each_operator_once();
+        if values.len() == 1 && values[0].0.0 == rules.target_value() && (!once || used == allowed)
+        {
+            return values.pop().map(|(_, expr)| expr);
+        }
+
+        values.sort_by_key(|&(item, _)| item);
+        let key = (values.iter().map(|&(item, _)| item).collect(), used);
+        if failed.contains(&key) {
+            return None;
+        }
+
+        // 使える演算子と、使った後の記録
+        let available = |op: Operator| !(once && used.contains(op));
+        let next_used = |op: Operator| if once { used.with(op) } else { used };
+
+        // 1つの数に単項演算を適用する
+        for i in 0..values.len() {
+            let ((value, _, unary_applied), _) = values[i];
+            if unary_applied {
+                continue;
+            }
+            for op in allowed.unary().filter(|&op| available(op)) {
+                if let Ok(result) = op.apply_unary(value)
+                    && result != value
+                    && rules.check_intermediate(result).is_ok()
+                {
+                    let mut next = values.clone();
+                    next[i] = ((result, false, true), Expr::unary(op, values[i].1.clone()));
+                    if let Some(expr) = Self::search(next, next_used(op), rules, failed) {
+                        return Some(expr);
+                    }
+                }
+            }
+        }
+
+        // 2つの数を二項演算で1つにまとめる
+        for i in 0..values.len() {
+            for j in 0..values.len() {
+                if i == j {
+                    continue;
+                }
+                let rest: Vec<SearchValue> = values
+                    .iter()
+                    .enumerate()
+                    .filter(|&(k, _)| k != i && k != j)
+                    .map(|(_, v)| v.clone())
+                    .collect();
+                let ((lhs, lhs_digits, _), _) = values[i];
+                let ((rhs, rhs_digits, _), _) = values[j];
+
+                for op in allowed.binary().filter(|&op| available(op)) {
+                    // 可換な演算は片方の順序だけ試せば十分
+                    if op.is_commutative() && i > j {
+                        continue;
+                    }
+                    // 連結できるのは数字を並べたものだけ
+                    let digits = op == Operator::Concat;
+                    if digits && !(lhs_digits && rhs_digits) {
+                        continue;
+                    }
+                    if let Ok(value) = apply_operator(op, lhs, rhs)
+                        && rules.check_intermediate(value).is_ok()
+                    {
+                        let expr = Expr::binary(op, values[i].1.clone(), values[j].1.clone());
+                        let mut next = rest.clone();
+                        next.push(((value, digits, false), expr));
+                        if let Some(expr) = Self::search(next, next_used(op), rules, failed) {
+                            return Some(expr);
                         }
                     }
                 }
             }
         }
-        false
+
+        failed.insert(key);
+        None
     }
 
-    /// 特定の数字と演算子の組み合わせで10になるかチェック
-    fn try_combination(nums: [u8; 4], op1: char, op2: char, op3: char) -> bool {
-        let [a, b, c, d] = nums.map(|x| x as f64);
+    /// 目標の数を作るすべての式を列挙
+    ///
+    /// すべての数字の並びと括弧の付け方（二分木の形）と演算子の組み合わせを試し、
+    /// 値が目標の数になる式木を返す。同じ値の数字を入れ替えただけの式は1つにまとめる。
+    /// 途中の式もすべて作るため、5枚以上の盤面では非常に重い。
+    pub fn all_solutions(numbers: &GameNumbers, rules: &RuleSet) -> Vec<Expr> {
+        let digits = &numbers.digits;
+        let full_mask = (1u32 << digits.len()) - 1;
+        let target = rules.target_value();
+        let allowed = rules.allowed_operators();
 
-        // 左から右への計算: ((a op1 b) op2 c) op3 d
-        let result1 = Self::apply_ops(Self::apply_ops(Self::apply_ops(a, op1, b), op2, c), op3, d);
+        let mut memo = HashMap::new();
+        let mut solutions: Vec<Expr> = Self::enumerate_subset(digits, full_mask, rules, &mut memo)
+            .iter()
+            .filter(|(value, _, used)| {
+                *value == target && (!rules.each_operator_once() || *used == allowed)
+            })
+            .map(|(_, expr, _)| expr.clone())
+            .collect();
 
-        // 括弧の位置を変えた計算: (a op1 b) op2 (c op3 d)
-        let result2 = Self::apply_ops(Self::apply_ops(a, op1, b), op2, Self::apply_ops(c, op3, d));
+        // 同じ値のカードを入れ替えただけの式は表示が同じなので1つにまとめる
+        let mut seen = HashSet::new();
+        solutions.retain(|expr| seen.insert(expr.to_string()));
+        solutions
+    }
 
-        // 異なる括弧: a op1 ((b op2 c) op3 d)
-        let result3 = Self::apply_ops(a, op1, Self::apply_ops(Self::apply_ops(b, op2, c), op3, d));
+    /// 本質的に異なる解を列挙（正規形ごとに代表の式を1つ返す）
+    pub fn distinct_solutions(numbers: &GameNumbers, rules: &RuleSet) -> Vec<Expr> {
+        Self::distinct_solutions_up_to(numbers, rules, usize::MAX)
+    }
 
-        // 異なる括弧: a op1 (b op2 (c op3 d))
-        let result4 = Self::apply_ops(a, op1, Self::apply_ops(b, op2, Self::apply_ops(c, op3, d)));
+    /// 本質的に異なる解を`limit`個まで列挙
+    ///
+    /// すべての式を列挙せずに済むため、枚数の多い盤面ではこちらを使う。
+    pub fn distinct_solutions_up_to(
+        numbers: &GameNumbers,
+        rules: &RuleSet,
+        limit: usize,
+    ) -> Vec<Expr> {
+        SolutionSpace::new(numbers, rules).distinct_solutions(limit)
+    }
 
-        // 異なる括弧: (a op1 (b op2 c)) op3 d
-        let result5 = Self::apply_ops(Self::apply_ops(a, op1, Self::apply_ops(b, op2, c)), op3, d);
+    /// 本質的に異なる解の数
+    pub fn count_distinct_solutions(numbers: &GameNumbers, rules: &RuleSet) -> usize {
+        Self::distinct_solutions(numbers, rules).len()
+    }
 
-        // 10に近い値（浮動小数点の誤差を考慮）
-        [result1, result2, result3, result4, result5]
-            .iter()
-            .any(|&r| (r - 10.0).abs() < 1e-10)
-    }
-
-    /// 2つの数値に演算子を適用
-    fn apply_ops(a: f64, op: char, b: f64) -> f64 {
-        match op {
-            '+' => a + b,
-            '-' => a - b,
-            '*' => a * b,
-            '/' => {
-                if b.abs() > 1e-10 {
-                    a / b
-                } else {
-                    f64::NAN
+    /// 指定した数字の部分集合から作れるすべての式とその値を列挙
+    ///
+    /// 演算子を1回ずつ使うルールでは、同じ演算子を2回使う式は作らない。
+    fn enumerate_subset(
+        digits: &[u8],
+        mask: u32,
+        rules: &RuleSet,
+        memo: &mut HashMap<u32, Vec<Enumerated>>,
+    ) -> Vec<Enumerated> {
+        if let Some(exprs) = memo.get(&mask) {
+            return exprs.clone();
+        }
+
+        let allowed = rules.allowed_operators();
+        let once = rules.each_operator_once();
+        let mut exprs = Vec::new();
+        if mask.count_ones() == 1 {
+            let index = mask.trailing_zeros() as usize;
+            let digit = digits[index];
+            exprs.push((
+                Rational::from(digit),
+                Expr::literal(index, digit),
+                OperatorSet::EMPTY,
+            ));
+        } else {
+            // 部分集合を左右に分割して組み合わせる
+            let mut left_mask = (mask - 1) & mask;
+            while left_mask > 0 {
+                let right_mask = mask & !left_mask;
+                let lefts = Self::enumerate_subset(digits, left_mask, rules, memo);
+                let rights = Self::enumerate_subset(digits, right_mask, rules, memo);
+
+                for (left_value, left_expr, left_used) in &lefts {
+                    for (right_value, right_expr, right_used) in &rights {
+                        let used = left_used.union(*right_used);
+                        if once && !left_used.is_disjoint(*right_used) {
+                            continue;
+                        }
+                        for op in allowed.binary() {
+                            if once && used.contains(op) {
+                                continue;
+                            }
+                            if op == Operator::Concat
+                                && !(left_expr.is_digit_string() && right_expr.is_digit_string())
+                            {
+                                continue;
+                            }
+                            if let Ok(value) = apply_operator(op, *left_value, *right_value)
+                                && rules.check_intermediate(value).is_ok()
+                            {
+                                let expr = Expr::binary(op, left_expr.clone(), right_expr.clone());
+                                exprs.push((value, expr, used.with(op)));
+                            }
+                        }
+                    }
                 }
+
+                left_mask = (left_mask - 1) & mask;
             }
-            _ => f64::NAN,
         }
-    }
 
-    /// 与えられた式が10になるかチェック（簡易版）
-    pub fn evaluate_expression(expression: &str, numbers: &GameNumbers) -> CalculationResult {
-        // 簡単な加算式のみサポート（将来拡張予定）
-        if expression == "1 + 2 + 3 + 4" && numbers.digits == [1, 2, 3, 4] {
-            CalculationResult {
-                result: 10.0,
-                is_valid: true,
-                used_all_digits: true,
-            }
-        } else {
-            CalculationResult {
-                result: 0.0,
-                is_valid: false,
-                used_all_digits: false,
+        // 単項演算を適用した式を追加する（続けて適用はしない）
+        let mut unary_exprs = Vec::new();
+        for (value, expr, used) in &exprs {
+            for op in allowed.unary() {
+                if once && used.contains(op) {
+                    continue;
+                }
+                if let Ok(result) = op.apply_unary(*value)
+                    && result != *value
+                    && rules.check_intermediate(result).is_ok()
+                {
+                    unary_exprs.push((result, Expr::unary(op, expr.clone()), used.with(op)));
+                }
             }
         }
+        exprs.extend(unary_exprs);
+
+        memo.insert(mask, exprs.clone());
+        exprs
+    }
+
+    /// 式を解析・評価し、与えられた数字の使用状況を検証する
+    pub fn evaluate_expression(
+        expression: &str,
+        numbers: &GameNumbers,
+    ) -> Result<CalculationResult, CalculationError> {
+        let expr = Expr::parse_with_numbers(expression, numbers)?;
+        Ok(CalculationResult {
+            result: expr.evaluate()?,
+            used_all_digits: expr.uses_all_numbers(numbers),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::game::RuleVariant;
 
     #[test]
     fn test_can_make_ten_with_solvable_numbers() {
         // テスト: 解答可能な数字の組み合わせで10が作れることを確認
         // 例: [1, 2, 3, 4] -> 4 * (3 - 1) + 2 = 10
         let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
-
-        // 現在の実装では失敗するはず
         assert!(Calculator::can_make_ten(&numbers));
     }
 
@@ -143,15 +374,401 @@ mod tests {
         assert!(Calculator::can_make_ten(&numbers));
     }
 
+    #[test]
+    fn test_can_make_ten_with_fractional_intermediate() {
+        // テスト: 途中で分数になる組み合わせ
+        // 例: [1, 1, 5, 8] -> 8 / (1 - 1 / 5) = 10
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        assert!(Calculator::can_make_ten(&numbers));
+    }
+
+    #[test]
+    fn test_all_solutions_evaluate_to_ten() {
+        // テスト: 列挙された解がすべて10になり、すべての数字を1回ずつ使っている
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let solutions = Calculator::all_solutions(&numbers, &RuleSet::default());
+
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            assert_eq!(solution.evaluate(), Ok(Rational::from_integer(10)));
+            let result = Calculator::evaluate_expression(&solution.to_string(), &numbers).unwrap();
+            assert!(
+                result.used_all_digits,
+                "{} does not use all digits",
+                solution
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_solutions_for_one_one_nine_nine() {
+        // テスト: [1, 1, 9, 9] の解は (1 / 9 + 1) * 9 の形のみ
+        let numbers = GameNumbers::from_digits([1, 1, 9, 9]);
+        let mut solutions: Vec<String> = Calculator::all_solutions(&numbers, &RuleSet::default())
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect();
+        solutions.sort();
+
+        assert_eq!(
+            solutions,
+            vec![
+                "(1 + 1 / 9) * 9",
+                "(1 / 9 + 1) * 9",
+                "9 * (1 + 1 / 9)",
+                "9 * (1 / 9 + 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_all_solutions_agrees_with_can_make_ten() {
+        // テスト: 解の有無がcan_make_tenと一致する
+        for digits in [[0, 0, 0, 0], [1, 1, 1, 1], [1, 1, 5, 8], [2, 5, 5, 0]] {
+            let numbers = GameNumbers::from_digits(digits);
+            assert_eq!(
+                !Calculator::all_solutions(&numbers, &RuleSet::default()).is_empty(),
+                Calculator::can_make_ten(&numbers),
+                "{:?}",
+                digits
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_distinct_solutions() {
+        // テスト: [1, 1, 9, 9] の本質的な解は1つ
+        let numbers = GameNumbers::from_digits([1, 1, 9, 9]);
+        assert_eq!(
+            Calculator::count_distinct_solutions(&numbers, &RuleSet::default()),
+            1
+        );
+
+        // 重複を除いても複数の解がある
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let distinct = Calculator::distinct_solutions(&numbers, &RuleSet::default());
+        assert!(distinct.len() > 1);
+        assert!(distinct.len() < Calculator::all_solutions(&numbers, &RuleSet::default()).len());
+        for (i, a) in distinct.iter().enumerate() {
+            for b in &distinct[i + 1..] {
+                assert!(!a.is_equivalent(b), "{} and {} are equivalent", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_can_make_target_twenty_four() {
+        // テスト: Make 24 の定番問題
+        let rules = RuleSet::with_target(24);
+        for digits in [[3, 3, 8, 8], [1, 2, 3, 4], [1, 5, 5, 5], [1, 3, 4, 6]] {
+            let numbers = GameNumbers::from_digits(digits);
+            assert!(
+                Calculator::can_make_target(&numbers, &rules),
+                "{:?}",
+                digits
+            );
+        }
+
+        let numbers = GameNumbers::from_digits([1, 1, 1, 1]);
+        assert!(!Calculator::can_make_target(&numbers, &rules));
+    }
+
+    #[test]
+    fn test_all_solutions_for_three_three_eight_eight() {
+        // テスト: [3, 3, 8, 8] で24を作る本質的な解は 8 / (3 - 8 / 3) のみ
+        let numbers = GameNumbers::from_digits([3, 3, 8, 8]);
+        let rules = RuleSet::with_target(24);
+        let solutions = Calculator::distinct_solutions(&numbers, &rules);
+
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].to_string(), "8 / (3 - 8 / 3)");
+    }
+
+    #[test]
+    fn test_can_make_target_with_other_digit_counts() {
+        // テスト: 3枚・5枚・6枚でも探索できる
+        let rules = RuleSet::default();
+        assert!(Calculator::can_make_target(
+            &GameNumbers::from_digits([2, 3, 4]),
+            &rules
+        )); // 2 * 3 + 4
+        assert!(!Calculator::can_make_target(
+            &GameNumbers::from_digits([1, 1, 1]),
+            &rules
+        ));
+        assert!(Calculator::can_make_target(
+            &GameNumbers::from_digits([1, 1, 1, 1, 6]),
+            &rules
+        )); // 1 + 1 + 1 + 1 + 6
+        assert!(Calculator::can_make_target(
+            &GameNumbers::from_digits([9, 9, 9, 9, 9, 9]),
+            &rules
+        )); // 9 + 9 / 9 + (9 - 9) * 9
+        assert!(!Calculator::can_make_target(
+            &GameNumbers::from_digits([1, 1, 1, 1, 1]),
+            &rules
+        ));
+    }
+
+    #[test]
+    fn test_find_solution() {
+        // テスト: 見つかった式はルールを守り、すべての数字を使って目標の数になる
+        let rules = RuleSet::default();
+        for digits in [vec![1, 1, 5, 8], vec![2, 3, 4], vec![9, 9, 9, 9, 9, 9]] {
+            let numbers = GameNumbers::from_digits(digits);
+            let solution = Calculator::find_solution(&numbers, &rules).unwrap();
+            assert_eq!(
+                rules.check_answer(&solution, &numbers),
+                Ok(()),
+                "{}",
+                solution
+            );
+        }
+
+        let rules = RuleSet::default().with_variant(RuleVariant::EachOperatorOnce);
+        let numbers = GameNumbers::from_digits([9, 5, 2, 4, 2]);
+        let solution = Calculator::find_solution(&numbers, &rules).unwrap();
+        assert_eq!(
+            rules.check_answer(&solution, &numbers),
+            Ok(()),
+            "{}",
+            solution
+        );
+
+        let numbers = GameNumbers::from_digits([1, 1, 1, 1]);
+        assert_eq!(
+            Calculator::find_solution(&numbers, &RuleSet::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_all_solutions_with_three_digits() {
+        // テスト: 3枚の解の列挙
+        let numbers = GameNumbers::from_digits([2, 3, 4]);
+        let solutions = Calculator::distinct_solutions(&numbers, &RuleSet::default());
+        let mut texts: Vec<String> = solutions.iter().map(|expr| expr.to_string()).collect();
+        texts.sort();
+        assert_eq!(texts, vec!["4 * 3 - 2", "4 + 3 * 2"]);
+        assert!(solutions.iter().all(|expr| expr.uses_all_numbers(&numbers)));
+    }
+
     #[test]
     fn test_evaluate_simple_expression() {
         // テスト: 簡単な式の評価
         let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
-        let result = Calculator::evaluate_expression("1 + 2 + 3 + 4", &numbers);
+        let result = Calculator::evaluate_expression("1 + 2 + 3 + 4", &numbers).unwrap();
 
-        // 現在の実装では失敗するはず
-        assert_eq!(result.result, 10.0);
-        assert!(result.is_valid);
+        assert_eq!(result.result, Rational::from_integer(10));
         assert!(result.used_all_digits);
     }
+
+    #[test]
+    fn test_evaluate_expression_with_precedence_and_parentheses() {
+        // テスト: 演算子優先度と括弧
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let result = Calculator::evaluate_expression("4 * (3 - 1) + 2", &numbers).unwrap();
+        assert_eq!(result.result, Rational::from_integer(10));
+        assert!(result.used_all_digits);
+
+        let result = Calculator::evaluate_expression("((1 + 2) * (3 + 4))", &numbers).unwrap();
+        assert_eq!(result.result, Rational::from_integer(21));
+    }
+
+    #[test]
+    fn test_evaluate_expression_with_unary_minus() {
+        // テスト: 単項マイナス
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let result = Calculator::evaluate_expression("-1 + 2 * 3 - -4", &numbers).unwrap();
+        assert_eq!(result.result, Rational::from_integer(9));
+        assert!(result.used_all_digits);
+    }
+
+    #[test]
+    fn test_evaluate_expression_is_exact() {
+        // テスト: 分数を経由しても誤差なく評価される
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        let result = Calculator::evaluate_expression("8 / (1 - 1 / 5)", &numbers).unwrap();
+        assert_eq!(result.result, Rational::from_integer(10));
+
+        let numbers = GameNumbers::from_digits([8, 3, 3, 2]);
+        let result = Calculator::evaluate_expression("8 / 3 * 3 + 2", &numbers).unwrap();
+        assert_eq!(result.result, Rational::from_integer(10));
+    }
+
+    #[test]
+    fn test_evaluate_expression_partial_use() {
+        // テスト: 一部の数字しか使っていない式
+        let numbers = GameNumbers::from_digits([1, 9, 3, 4]);
+        let result = Calculator::evaluate_expression("1 + 9", &numbers).unwrap();
+        assert_eq!(result.result, Rational::from_integer(10));
+        assert!(!result.used_all_digits);
+    }
+
+    #[test]
+    fn test_evaluate_expression_errors() {
+        // テスト: 各種エラー
+        let numbers = GameNumbers::from_digits([1, 2, 3, 0]);
+        assert_eq!(
+            Calculator::evaluate_expression("", &numbers),
+            Err(CalculationError::EmptyExpression)
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("1 $ 2", &numbers),
+            Err(CalculationError::UnknownToken('$'))
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("(1 + 2", &numbers),
+            Err(CalculationError::UnbalancedParentheses)
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("1 + 2)", &numbers),
+            Err(CalculationError::UnbalancedParentheses)
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("1 +", &numbers),
+            Err(CalculationError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("1 * + 2", &numbers),
+            Err(CalculationError::UnexpectedToken("+".to_string()))
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("2 + 2", &numbers),
+            Err(CalculationError::DigitReused(2))
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("3 / 0 + 1", &numbers),
+            Err(CalculationError::DivisionByZero)
+        );
+        assert_eq!(
+            Calculator::evaluate_expression("1 + 5", &numbers),
+            Err(CalculationError::DigitNotAvailable(5))
+        );
+    }
+
+    #[test]
+    fn test_extended_operators_make_more_boards_solvable() {
+        // テスト: 拡張演算子を有効にすると四則演算だけでは解けない問題が解ける
+        let cases = [
+            // 11 - 1 * 1 = 10
+            ([1, 1, 1, 1], 10, Operator::Concat),
+            // 2 ^ (2 + 2 + 2) = 64
+            ([2, 2, 2, 2], 64, Operator::Pow),
+            // (0! + 0! + 0! + 0!)! = 24
+            ([0, 0, 0, 0], 24, Operator::Factorial),
+        ];
+        for (digits, target, op) in cases {
+            let numbers = GameNumbers::from_digits(digits);
+            let basic = RuleSet::with_target(target);
+            assert!(
+                !Calculator::can_make_target(&numbers, &basic),
+                "{:?}",
+                digits
+            );
+
+            let rules = basic.with_operator(op);
+            assert!(
+                Calculator::can_make_target(&numbers, &rules),
+                "{:?}",
+                digits
+            );
+            let solutions = Calculator::all_solutions(&numbers, &rules);
+            assert!(!solutions.is_empty(), "{:?}", digits);
+            for solution in &solutions {
+                assert_eq!(solution.evaluate(), Ok(Rational::from_integer(target)));
+                assert!(solution.operators().contains(&op), "{}", solution);
+            }
+        }
+    }
+
+    #[test]
+    fn test_square_root_in_solutions() {
+        // テスト: 平方根を使った解が列挙され、式として再評価できる
+        let numbers = GameNumbers::from_digits([4, 4, 9, 1]);
+        let rules = RuleSet::default().with_operator(Operator::Sqrt);
+        let solutions = Calculator::all_solutions(&numbers, &rules);
+        assert!(
+            solutions
+                .iter()
+                .any(|expr| expr.contains_operator(Operator::Sqrt))
+        );
+        for solution in solutions {
+            let result = Calculator::evaluate_expression(&solution.to_string(), &numbers).unwrap();
+            assert_eq!(result.result, Rational::from_integer(10), "{}", solution);
+            assert!(result.used_all_digits);
+        }
+    }
+
+    #[test]
+    fn test_restricted_operators() {
+        // テスト: 割り算禁止では 8 / (1 - 1 / 5) が使えず [1, 1, 5, 8] は解けない
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        let rules = RuleSet::default().with_variant(RuleVariant::NoDivision);
+        assert!(!Calculator::can_make_target(&numbers, &rules));
+        assert!(Calculator::all_solutions(&numbers, &rules).is_empty());
+
+        // 足し算と掛け算のみでも [1, 2, 3, 4] は 1 + 2 + 3 + 4 で解ける
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let rules = RuleSet::default().with_variant(RuleVariant::OnlyAddMul);
+        assert!(Calculator::can_make_target(&numbers, &rules));
+        for solution in Calculator::all_solutions(&numbers, &rules) {
+            assert_eq!(rules.check(&solution), Ok(()), "{}", solution);
+        }
+    }
+
+    #[test]
+    fn test_each_operator_once() {
+        // テスト: 四則演算を1回ずつ使う解だけが見つかる
+        let rules = RuleSet::default().with_variant(RuleVariant::EachOperatorOnce);
+        let numbers = GameNumbers::from_digits([9, 5, 2, 4, 2]);
+        assert!(Calculator::can_make_target(&numbers, &rules));
+        let solutions = Calculator::all_solutions(&numbers, &rules);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            assert_eq!(solution.evaluate(), Ok(Rational::from_integer(10)));
+            assert_eq!(rules.check(solution), Ok(()), "{}", solution);
+        }
+
+        // 同じ演算子を繰り返さないと作れない盤面は解けない（2 + 2 + 2 + 2 + 2 など）
+        let numbers = GameNumbers::from_digits([2, 2, 2, 2, 2]);
+        assert!(Calculator::can_make_target(&numbers, &RuleSet::default()));
+        assert!(!Calculator::can_make_target(&numbers, &rules));
+    }
+
+    #[test]
+    fn test_integer_intermediates() {
+        // テスト: 途中の計算結果を整数に限ると [1, 1, 5, 8] は解けない
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        let rules = RuleSet::default().with_integer_intermediates(true);
+        assert!(Calculator::can_make_ten(&numbers));
+        assert!(!Calculator::can_make_target(&numbers, &rules));
+        assert!(Calculator::all_solutions(&numbers, &rules).is_empty());
+
+        // [1, 1, 9, 9] の唯一の解 (1 / 9 + 1) * 9 も分数を経由する
+        let numbers = GameNumbers::from_digits([1, 1, 9, 9]);
+        assert!(!Calculator::can_make_target(&numbers, &rules));
+
+        // 整数だけで解ける盤面の解はすべて整数を経由する
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let solutions = Calculator::all_solutions(&numbers, &rules);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            assert_eq!(rules.check(solution), Ok(()), "{}", solution);
+        }
+    }
+
+    #[test]
+    fn test_non_negative_intermediates() {
+        // テスト: 途中の計算結果を0以上に限ると負の数を経由する解は除かれる
+        let rules = RuleSet::default().with_non_negative_intermediates(true);
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let all = Calculator::all_solutions(&numbers, &RuleSet::default());
+        let solutions = Calculator::all_solutions(&numbers, &rules);
+        assert!(!solutions.is_empty());
+        assert!(solutions.len() < all.len());
+        for solution in &solutions {
+            assert_eq!(rules.check(solution), Ok(()), "{}", solution);
+        }
+    }
 }
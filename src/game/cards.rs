@@ -0,0 +1,211 @@
+//! カード合成モードの盤面
+//!
+//! 2枚のカードを選んで演算子を適用すると、2枚が結果の1枚に置き換わる。
+//! 最後の1枚になるまで繰り返すことで、括弧を入力せずに任意の式を組み立てられる。
+
+use crate::game::{CalculationError, Expr, GameNumbers, Operator, Rational, apply_operator};
+
+/// 盤面上の1枚のカード
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    /// このカードを作った式
+    pub expr: Expr,
+    /// カードの値
+    pub value: Rational,
+}
+
+/// カード合成モードの盤面
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CardBoard {
+    pub cards: Vec<Card>,
+    /// 取り消し用の過去の盤面
+    history: Vec<Vec<Card>>,
+}
+
+impl CardBoard {
+    /// 与えられた数字から盤面を作成
+    pub fn new(numbers: &GameNumbers) -> Self {
+        let cards = numbers
+            .digits
+            .iter()
+            .enumerate()
+            .map(|(index, &value)| Card {
+                expr: Expr::literal(index, value),
+                value: Rational::from(value),
+            })
+            .collect();
+
+        Self {
+            cards,
+            history: Vec::new(),
+        }
+    }
+
+    /// 2枚のカードに演算子を適用して1枚にまとめる
+    ///
+    /// 結果のカードは`first`の位置に置かれ、`second`は取り除かれる。
+    pub fn combine(
+        &mut self,
+        first: usize,
+        second: usize,
+        op: Operator,
+    ) -> Result<(), CalculationError> {
+        if first == second || first >= self.cards.len() || second >= self.cards.len() {
+            return Err(CalculationError::InvalidCardSelection);
+        }
+
+        let lhs = &self.cards[first];
+        let rhs = &self.cards[second];
+        // 連結できるのは数字を並べたカードだけ
+        if op == Operator::Concat && !(lhs.expr.is_digit_string() && rhs.expr.is_digit_string()) {
+            return Err(CalculationError::InvalidOperand(op));
+        }
+        let card = Card {
+            value: apply_operator(op, lhs.value, rhs.value)?,
+            expr: Expr::binary(op, lhs.expr.clone(), rhs.expr.clone()),
+        };
+
+        self.history.push(self.cards.clone());
+        self.cards[first] = card;
+        self.cards.remove(second);
+        Ok(())
+    }
+
+    /// 1枚のカードに単項演算子（階乗・平方根）を適用する
+    pub fn apply_unary(&mut self, index: usize, op: Operator) -> Result<(), CalculationError> {
+        let Some(card) = self.cards.get(index) else {
+            return Err(CalculationError::InvalidCardSelection);
+        };
+
+        let card = Card {
+            value: op.apply_unary(card.value)?,
+            expr: Expr::unary(op, card.expr.clone()),
+        };
+
+        self.history.push(self.cards.clone());
+        self.cards[index] = card;
+        Ok(())
+    }
+
+    /// 直前の合成を取り消す
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(cards) => {
+                self.cards = cards;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// カードが1枚になったか
+    pub fn is_complete(&self) -> bool {
+        self.cards.len() == 1
+    }
+
+    /// 最後の1枚（完成していない場合はNone）
+    pub fn result(&self) -> Option<&Card> {
+        if self.is_complete() {
+            self.cards.first()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_board_has_one_card_per_digit() {
+        // テスト: 数字ごとに1枚のカードが並ぶ
+        let board = CardBoard::new(&GameNumbers::from_digits([1, 2, 3, 4]));
+        assert_eq!(board.cards.len(), 4);
+        assert_eq!(board.cards[2].value, Rational::from_integer(3));
+        assert!(!board.is_complete());
+        assert!(board.result().is_none());
+    }
+
+    #[test]
+    fn test_combine_replaces_two_cards_with_result() {
+        // テスト: (1 + 4) * (9 - 7) = 10 を括弧なしで組み立てる
+        let numbers = GameNumbers::from_digits([1, 4, 9, 7]);
+        let mut board = CardBoard::new(&numbers);
+
+        board.combine(0, 1, Operator::Add).unwrap(); // [5, 9, 7]
+        assert_eq!(board.cards.len(), 3);
+        assert_eq!(board.cards[0].value, Rational::from_integer(5));
+
+        board.combine(1, 2, Operator::Sub).unwrap(); // [5, 2]
+        board.combine(0, 1, Operator::Mul).unwrap(); // [10]
+
+        let result = board.result().unwrap();
+        assert_eq!(result.value, Rational::from_integer(10));
+        assert_eq!(result.expr.to_string(), "(1 + 4) * (9 - 7)");
+        assert!(result.expr.uses_all_numbers(&numbers));
+    }
+
+    #[test]
+    fn test_combine_keeps_fractions_exact() {
+        // テスト: 8 / (1 - 1 / 5) = 10
+        let mut board = CardBoard::new(&GameNumbers::from_digits([1, 1, 5, 8]));
+        board.combine(1, 2, Operator::Div).unwrap(); // [1, 1/5, 8]
+        assert_eq!(board.cards[1].value, Rational::new(1, 5).unwrap());
+        board.combine(0, 1, Operator::Sub).unwrap(); // [4/5, 8]
+        board.combine(1, 0, Operator::Div).unwrap(); // [10]
+        assert_eq!(board.cards[0].value, Rational::from_integer(10));
+    }
+
+    #[test]
+    fn test_combine_errors_leave_board_unchanged() {
+        // テスト: ゼロ除算や不正な選択では盤面が変わらない
+        let mut board = CardBoard::new(&GameNumbers::from_digits([3, 0, 2, 5]));
+        let before = board.clone();
+        assert_eq!(
+            board.combine(0, 1, Operator::Div),
+            Err(CalculationError::DivisionByZero)
+        );
+        assert!(board.combine(2, 2, Operator::Add).is_err());
+        assert!(board.combine(0, 9, Operator::Add).is_err());
+        assert_eq!(board, before);
+    }
+
+    #[test]
+    fn test_undo_restores_previous_board() {
+        // テスト: 取り消しで直前の盤面に戻る
+        let mut board = CardBoard::new(&GameNumbers::from_digits([1, 2, 3, 4]));
+        let before = board.cards.clone();
+        board.combine(0, 3, Operator::Mul).unwrap();
+        assert!(board.undo());
+        assert_eq!(board.cards, before);
+        assert!(!board.undo());
+    }
+
+    #[test]
+    fn test_extended_operators_on_cards() {
+        // テスト: 連結と単項演算子でカードを作る
+        let mut board = CardBoard::new(&GameNumbers::from_digits([1, 2, 3, 4]));
+        board.combine(0, 1, Operator::Concat).unwrap(); // [12, 3, 4]
+        board.apply_unary(1, Operator::Factorial).unwrap(); // [12, 6, 4]
+        assert_eq!(board.cards[1].value, Rational::from_integer(6));
+        assert_eq!(board.cards[1].expr.to_string(), "3!");
+
+        // 計算結果は連結できない
+        board.combine(1, 2, Operator::Add).unwrap(); // [12, 10]
+        let before = board.clone();
+        assert_eq!(
+            board.combine(0, 1, Operator::Concat),
+            Err(CalculationError::InvalidOperand(Operator::Concat))
+        );
+        assert_eq!(board, before);
+
+        // 平方数でない値の平方根は取れない
+        assert!(board.apply_unary(1, Operator::Sqrt).is_err());
+        assert!(board.apply_unary(5, Operator::Sqrt).is_err());
+        assert_eq!(board, before);
+
+        assert!(board.undo());
+        assert_eq!(board.cards[1].value, Rational::from_integer(6));
+    }
+}
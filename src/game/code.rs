@@ -0,0 +1,399 @@
+//! 盤面を共有するためのパズルコード
+//!
+//! コードは次のバイト列をCrockford形式のbase32で表したもの。
+//!
+//! | バイト | 内容 |
+//! |---|---|
+//! | 0 | 上位4ビット: バージョン、下位4ビット: 数字の枚数 |
+//! | 1.. | 数字を4ビットずつ詰めたもの |
+//! | 続く2バイト | 目標の数（符号付き16ビット、ビッグエンディアン） |
+//! | 続く1バイト | ルールのフラグ（有効な演算子の四則演算との差分） |
+//! | 続く1バイト | 下位4ビット: 特別ルール、上位4ビット: 途中結果の制限（標準のルールでは省略） |
+//! | 最後の1バイト | チェックサム |
+
+use crate::game::{Calculator, GameNumbers, OperatorSet, RuleSet, RuleVariant};
+
+/// コード形式のバージョン
+const CODE_VERSION: u8 = 1;
+
+/// base32の文字（紛らわしいI, L, O, Uを除く）
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// 読みやすさのために区切りを入れる文字数
+const GROUP_SIZE: usize = 4;
+
+/// パズルコードの変換で発生するエラー
+#[derive(Debug, Clone, PartialEq)]
+pub enum PuzzleCodeError {
+    /// base32として解釈できない文字
+    InvalidCharacter(char),
+    /// コードの長さが合わない
+    InvalidLength,
+    /// 対応していないバージョン
+    UnsupportedVersion(u8),
+    /// チェックサムが一致しない（入力ミスなど）
+    ChecksumMismatch,
+    /// 0-9以外の数字
+    InvalidDigit(u8),
+    /// 数字の枚数がコードで表せない
+    InvalidDigitCount(usize),
+    /// 目標の数がコードで表せない
+    TargetOutOfRange(i64),
+    /// 対応していないルールのフラグ
+    UnsupportedFlags(u8),
+    /// 復元したルールでは解けない盤面
+    Unsolvable,
+}
+
+impl std::fmt::Display for PuzzleCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleCodeError::InvalidCharacter(c) => write!(f, "invalid character '{}'", c),
+            PuzzleCodeError::InvalidLength => write!(f, "invalid code length"),
+            PuzzleCodeError::UnsupportedVersion(v) => write!(f, "unsupported code version {}", v),
+            PuzzleCodeError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            PuzzleCodeError::InvalidDigit(d) => write!(f, "invalid digit {}", d),
+            PuzzleCodeError::InvalidDigitCount(n) => write!(f, "invalid number of digits {}", n),
+            PuzzleCodeError::TargetOutOfRange(t) => write!(f, "target {} is out of range", t),
+            PuzzleCodeError::UnsupportedFlags(flags) => {
+                write!(f, "unsupported rule flags {:#04x}", flags)
+            }
+            PuzzleCodeError::Unsolvable => write!(f, "the board has no solution"),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleCodeError {}
+
+impl GameNumbers {
+    /// 盤面とルールをパズルコードに変換（"XXXX-XXXX-XXXX" の形式）
+    pub fn to_code(&self, rules: &RuleSet) -> Result<String, PuzzleCodeError> {
+        let count = self.digits.len();
+        if !RuleSet::DIGIT_COUNTS.contains(&count) {
+            return Err(PuzzleCodeError::InvalidDigitCount(count));
+        }
+        if let Some(&digit) = self.digits.iter().find(|&&d| d > 9) {
+            return Err(PuzzleCodeError::InvalidDigit(digit));
+        }
+        let target = i16::try_from(rules.target)
+            .map_err(|_| PuzzleCodeError::TargetOutOfRange(rules.target))?;
+
+        let mut bytes = vec![(CODE_VERSION << 4) | count as u8];
+        for pair in self.digits.chunks(2) {
+            bytes.push((pair[0] << 4) | pair.get(1).copied().unwrap_or(0));
+        }
+        bytes.extend_from_slice(&target.to_be_bytes());
+        // 演算子のフラグ（四則演算との差分なので、標準のルールでは0）
+        bytes.push(rules.operators.bits() ^ OperatorSet::BASIC.bits());
+        let extra = rules.variant.to_byte()
+            | (rules.non_negative_intermediates as u8) << 4
+            | (rules.integer_intermediates as u8) << 5;
+        if extra != 0 {
+            bytes.push(extra);
+        }
+        bytes.push(checksum(&bytes));
+
+        Ok(group(&encode_base32(&bytes)))
+    }
+
+    /// パズルコードから盤面とルールを復元
+    ///
+    /// 大文字・小文字、区切りの"-"や空白は区別しない。
+    /// ゲームで扱えない枚数の盤面や、復元したルールでは解けない盤面はエラーになる。
+    pub fn from_code(code: &str) -> Result<(Self, RuleSet), PuzzleCodeError> {
+        let bytes = decode_base32(code)?;
+        let (&header, rest) = bytes.split_first().ok_or(PuzzleCodeError::InvalidLength)?;
+
+        let version = header >> 4;
+        if version != CODE_VERSION {
+            return Err(PuzzleCodeError::UnsupportedVersion(version));
+        }
+        let count = (header & 0x0f) as usize;
+        if !RuleSet::DIGIT_COUNTS.contains(&count) {
+            return Err(PuzzleCodeError::InvalidDigitCount(count));
+        }
+
+        // ヘッダ + 数字 + 目標2バイト + フラグ + (特別ルール) + チェックサム
+        let digit_bytes = count.div_ceil(2);
+        if rest.len() != digit_bytes + 4 && rest.len() != digit_bytes + 5 {
+            return Err(PuzzleCodeError::InvalidLength);
+        }
+        let (payload, check) = bytes.split_at(bytes.len() - 1);
+        if checksum(payload) != check[0] {
+            return Err(PuzzleCodeError::ChecksumMismatch);
+        }
+
+        let digits: Vec<u8> = rest[..digit_bytes]
+            .iter()
+            .flat_map(|&byte| [byte >> 4, byte & 0x0f])
+            .take(count)
+            .collect();
+        if let Some(&digit) = digits.iter().find(|&&d| d > 9) {
+            return Err(PuzzleCodeError::InvalidDigit(digit));
+        }
+
+        let target = i16::from_be_bytes([rest[digit_bytes], rest[digit_bytes + 1]]);
+        let flags = rest[digit_bytes + 2];
+        let operators = OperatorSet::from_bits(flags ^ OperatorSet::BASIC.bits());
+        if operators.binary().next().is_none() {
+            return Err(PuzzleCodeError::UnsupportedFlags(flags));
+        }
+
+        let extra = if rest.len() == digit_bytes + 5 {
+            rest[digit_bytes + 3]
+        } else {
+            0
+        };
+        let variant = RuleVariant::from_byte(extra & 0x0f)
+            .filter(|_| extra & 0xc0 == 0)
+            .ok_or(PuzzleCodeError::UnsupportedFlags(extra))?;
+
+        let rules = RuleSet::with_target(target as i64)
+            .with_digit_count(count)
+            .with_operators(operators)
+            .with_variant(variant)
+            .with_non_negative_intermediates(extra & 0x10 != 0)
+            .with_integer_intermediates(extra & 0x20 != 0);
+        let numbers = Self::from_digits(digits);
+        if !Calculator::can_make_target(&numbers, &rules) {
+            return Err(PuzzleCodeError::Unsolvable);
+        }
+        Ok((numbers, rules))
+    }
+}
+
+/// 1バイトのチェックサム（CRC-8、多項式0x07）
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        text.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    text
+}
+
+fn decode_base32(code: &str) -> Result<Vec<u8>, PuzzleCodeError> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in code.chars().filter(|c| *c != '-' && !c.is_whitespace()) {
+        // 見間違えやすい文字は同じ値として扱う
+        let normalized = match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            other => other,
+        };
+        let value = ALPHABET
+            .iter()
+            .position(|&a| a as char == normalized)
+            .ok_or(PuzzleCodeError::InvalidCharacter(c))?;
+
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+/// 4文字ごとに"-"で区切る
+fn group(text: &str) -> String {
+    text.as_bytes()
+        .chunks(GROUP_SIZE)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Operator;
+
+    #[test]
+    fn test_round_trip() {
+        // テスト: コードに変換して元の盤面とルールに戻せる
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        let rules = RuleSet::default();
+        let code = numbers.to_code(&rules).unwrap();
+        assert_eq!(code.len(), 14); // 12文字 + 区切り2つ
+
+        let (decoded, decoded_rules) = GameNumbers::from_code(&code).unwrap();
+        assert_eq!(decoded, numbers);
+        assert_eq!(decoded_rules, rules);
+    }
+
+    #[test]
+    fn test_round_trip_with_custom_rules() {
+        // テスト: 目標の数と枚数もコードに含まれる
+        let numbers = GameNumbers::from_digits([3, 3, 8, 8, 9]);
+        let rules = RuleSet::with_target(-24).with_digit_count(5);
+        let code = numbers.to_code(&rules).unwrap();
+        assert_eq!(GameNumbers::from_code(&code).unwrap(), (numbers, rules));
+    }
+
+    #[test]
+    fn test_round_trip_with_operators() {
+        // テスト: 有効な演算子もコードに含まれる
+        let numbers = GameNumbers::from_digits([2, 2, 2, 2]);
+        let rules = RuleSet::with_target(64)
+            .with_operators(OperatorSet::BASIC.without(Operator::Div))
+            .with_operator(Operator::Pow)
+            .with_operator(Operator::Sqrt);
+        let code = numbers.to_code(&rules).unwrap();
+        assert_ne!(code, numbers.to_code(&RuleSet::with_target(64)).unwrap());
+        assert_eq!(GameNumbers::from_code(&code).unwrap(), (numbers, rules));
+    }
+
+    #[test]
+    fn test_round_trip_with_variant() {
+        // テスト: 特別ルールもコードに含まれる
+        let numbers = GameNumbers::from_digits([1, 1, 1, 2, 5]);
+        for variant in RuleVariant::SPECIALS {
+            let rules = RuleSet::default().with_digit_count(5).with_variant(variant);
+            let code = numbers.to_code(&rules).unwrap();
+            assert_eq!(
+                GameNumbers::from_code(&code).unwrap(),
+                (numbers.clone(), rules)
+            );
+        }
+
+        // 途中結果の制限もコードに含まれる
+        let rules = RuleSet::default()
+            .with_digit_count(5)
+            .with_variant(RuleVariant::NoDivision)
+            .with_non_negative_intermediates(true)
+            .with_integer_intermediates(true);
+        let code = numbers.to_code(&rules).unwrap();
+        assert_eq!(GameNumbers::from_code(&code).unwrap(), (numbers, rules));
+    }
+
+    #[test]
+    fn test_code_is_case_and_separator_insensitive() {
+        // テスト: 小文字や区切りの有無に関係なく読める
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let code = numbers.to_code(&RuleSet::default()).unwrap();
+        let loose = code.replace('-', " ").to_lowercase();
+        assert_eq!(GameNumbers::from_code(&loose).unwrap().0, numbers);
+    }
+
+    #[test]
+    fn test_typo_is_detected() {
+        // テスト: 1文字の入力ミスはチェックサムなどで検出される
+        let code = GameNumbers::from_digits([1, 2, 3, 4])
+            .to_code(&RuleSet::default())
+            .unwrap();
+        let mut chars: Vec<char> = code.chars().collect();
+        chars[5] = if chars[5] == 'Z' { 'Y' } else { 'Z' };
+        let typo: String = chars.into_iter().collect();
+        assert!(GameNumbers::from_code(&typo).is_err());
+    }
+
+    #[test]
+    fn test_invalid_codes() {
+        // テスト: 不正なコードはエラー
+        assert_eq!(
+            GameNumbers::from_code("U"),
+            Err(PuzzleCodeError::InvalidCharacter('U'))
+        );
+        assert_eq!(
+            GameNumbers::from_code(""),
+            Err(PuzzleCodeError::InvalidLength)
+        );
+        assert_eq!(
+            GameNumbers::from_digits([1, 2, 3]).to_code(&RuleSet::with_target(100_000)),
+            Err(PuzzleCodeError::TargetOutOfRange(100_000))
+        );
+    }
+
+    #[test]
+    fn test_oversized_codes_are_rejected() {
+        // テスト: ゲームで扱えない枚数の盤面はコードにできず、そのようなコードも読めない
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4, 5, 6, 7]);
+        let rules = RuleSet::default().with_digit_count(7);
+        assert_eq!(
+            numbers.to_code(&rules),
+            Err(PuzzleCodeError::InvalidDigitCount(7))
+        );
+
+        // 7枚の盤面のコードを直接組み立てる
+        let mut bytes = vec![
+            (CODE_VERSION << 4) | 7,
+            0x12,
+            0x34,
+            0x56,
+            0x70,
+            0x00,
+            0x0a,
+            0x00,
+        ];
+        bytes.push(checksum(&bytes));
+        let code = group(&encode_base32(&bytes));
+        assert_eq!(
+            GameNumbers::from_code(&code),
+            Err(PuzzleCodeError::InvalidDigitCount(7))
+        );
+        assert_eq!(
+            GameNumbers::from_digits([1, 2]).to_code(&RuleSet::default()),
+            Err(PuzzleCodeError::InvalidDigitCount(2))
+        );
+    }
+
+    #[test]
+    fn test_six_card_round_trip() {
+        // テスト: 6枚の盤面もコードで共有できる
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4, 5, 6]);
+        let rules = RuleSet::default().with_digit_count(6);
+        let code = numbers.to_code(&rules).unwrap();
+        assert_eq!(GameNumbers::from_code(&code).unwrap(), (numbers, rules));
+    }
+
+    #[test]
+    fn test_unsolvable_codes_are_rejected() {
+        // テスト: 復元したルールで解けない盤面のコードはエラー
+        let numbers = GameNumbers::from_digits([1, 1, 1, 1]);
+        let code = numbers.to_code(&RuleSet::default()).unwrap();
+        assert_eq!(
+            GameNumbers::from_code(&code),
+            Err(PuzzleCodeError::Unsolvable)
+        );
+
+        // 同じ盤面でもルールによって解ける場合と解けない場合がある
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        let code = numbers.to_code(&RuleSet::default()).unwrap();
+        assert!(GameNumbers::from_code(&code).is_ok());
+        let rules = RuleSet::default().with_integer_intermediates(true);
+        let code = numbers.to_code(&rules).unwrap();
+        assert_eq!(
+            GameNumbers::from_code(&code),
+            Err(PuzzleCodeError::Unsolvable)
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! 日付から決まるデイリーパズル
+
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// 1日に出題する盤面の数
+pub const DAILY_PUZZLE_COUNT: u32 = 5;
+
+/// デイリー記録を保存するファイル
+///
+/// 相対パスのため、ゲームを起動したときのカレントディレクトリに保存される。
+/// 別のディレクトリから起動すると記録は引き継がれない。
+pub const DAILY_RECORDS_PATH: &str = "daily_records.txt";
+
+/// 暦の日付
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DailyDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl DailyDate {
+    pub fn new(year: i32, month: u32, day: u32) -> Self {
+        Self { year, month, day }
+    }
+
+    /// 今日の日付（UTC）
+    ///
+    /// 標準ライブラリではタイムゾーンを扱えないため、全員が同じ盤面になるようUTCで揃える。
+    pub fn today() -> Self {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Self::from_days_since_epoch((secs / 86_400) as i64)
+    }
+
+    /// 1970-01-01からの日数から日付を求める
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        // 3月始まりの400年周期で計算する
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+
+        Self { year, month, day }
+    }
+
+    /// "YYYY-MM-DD"形式から解析
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        ((1..=12).contains(&month) && (1..=Self::days_in_month(year, month)).contains(&day))
+            .then_some(Self { year, month, day })
+    }
+
+    /// その年の月の日数（うるう年を考慮）
+    pub fn days_in_month(year: i32, month: u32) -> u32 {
+        match month {
+            2 if Self::is_leap_year(year) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    /// うるう年かどうか（グレゴリオ暦）
+    pub fn is_leap_year(year: i32) -> bool {
+        year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+    }
+}
+
+impl fmt::Display for DailyDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// 進行中のデイリーチャレンジ
+#[derive(Debug, Clone, Default, Resource)]
+pub struct DailyChallenge {
+    /// 挑戦中の日付（通常モードではNone）
+    pub date: Option<DailyDate>,
+    /// 現在の盤面の番号（0始まり）
+    pub index: u32,
+    /// 開始時刻（秒）
+    pub started_at: f64,
+}
+
+impl DailyChallenge {
+    /// 指定した日付のデイリーチャレンジを開始
+    pub fn start(&mut self, date: DailyDate, now: f64) {
+        self.date = Some(date);
+        self.index = 0;
+        self.started_at = now;
+    }
+
+    /// デイリーチャレンジ中か
+    pub fn is_active(&self) -> bool {
+        self.date.is_some()
+    }
+
+    /// 次の盤面に進む
+    ///
+    /// 最後の盤面をクリアした場合は、終了した日付と経過時間（秒）を返す。
+    pub fn advance(&mut self, now: f64) -> Option<(DailyDate, f64)> {
+        let date = self.date?;
+        self.index += 1;
+        if self.index < DAILY_PUZZLE_COUNT {
+            return None;
+        }
+
+        self.date = None;
+        self.index = 0;
+        Some((date, now - self.started_at))
+    }
+}
+
+/// デイリーチャレンジの完了記録（日付ごとの最短時間）
+#[derive(Debug, Clone, Default, PartialEq, Resource)]
+pub struct DailyRecords {
+    times: BTreeMap<DailyDate, f64>,
+}
+
+impl DailyRecords {
+    /// ファイルから読み込む（存在しない場合は空の記録）
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        std::fs::read_to_string(path)
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// ファイルに保存
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    /// "YYYY-MM-DD 秒数"の行から解析（解釈できない行は無視）
+    pub fn parse(text: &str) -> Self {
+        let times = text
+            .lines()
+            .filter_map(|line| {
+                let (date, time) = line.split_once(' ')?;
+                Some((DailyDate::parse(date)?, time.trim().parse().ok()?))
+            })
+            .collect();
+
+        Self { times }
+    }
+
+    /// 保存用のテキスト
+    pub fn to_text(&self) -> String {
+        self.times
+            .iter()
+            .map(|(date, time)| format!("{} {:.1}\n", date, time))
+            .collect()
+    }
+
+    /// 完了時間を記録（既存の記録より速い場合のみ更新）
+    pub fn record(&mut self, date: DailyDate, time: f64) {
+        let best = self.times.entry(date).or_insert(time);
+        *best = best.min(time);
+    }
+
+    /// 指定した日付の完了時間
+    pub fn time_for(&self, date: DailyDate) -> Option<f64> {
+        self.times.get(&date).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_from_days_since_epoch() {
+        // テスト: 日数から日付への変換
+        assert_eq!(
+            DailyDate::from_days_since_epoch(0),
+            DailyDate::new(1970, 1, 1)
+        );
+        assert_eq!(
+            DailyDate::from_days_since_epoch(11_016),
+            DailyDate::new(2000, 2, 29)
+        );
+        assert_eq!(
+            DailyDate::from_days_since_epoch(19_723),
+            DailyDate::new(2024, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_date_display_and_parse() {
+        // テスト: 表示と解析が往復する
+        let date = DailyDate::new(2024, 3, 7);
+        assert_eq!(date.to_string(), "2024-03-07");
+        assert_eq!(DailyDate::parse("2024-03-07"), Some(date));
+        assert_eq!(DailyDate::parse("2024-13-07"), None);
+        assert_eq!(DailyDate::parse("today"), None);
+    }
+
+    #[test]
+    fn test_parse_checks_month_length() {
+        // テスト: 月の日数を超える日付は受け付けない
+        assert_eq!(DailyDate::parse("2026-02-31"), None);
+        assert_eq!(DailyDate::parse("2026-04-31"), None);
+        assert_eq!(
+            DailyDate::parse("2026-01-31"),
+            Some(DailyDate::new(2026, 1, 31))
+        );
+        // うるう年の2月29日だけを受け付ける
+        assert_eq!(DailyDate::parse("2023-02-29"), None);
+        assert_eq!(
+            DailyDate::parse("2024-02-29"),
+            Some(DailyDate::new(2024, 2, 29))
+        );
+        assert_eq!(DailyDate::parse("1900-02-29"), None);
+        assert_eq!(
+            DailyDate::parse("2000-02-29"),
+            Some(DailyDate::new(2000, 2, 29))
+        );
+    }
+
+    #[test]
+    fn test_challenge_finishes_after_all_puzzles() {
+        // テスト: すべての盤面をクリアすると経過時間が返る
+        let date = DailyDate::new(2024, 3, 7);
+        let mut challenge = DailyChallenge::default();
+        challenge.start(date, 10.0);
+
+        for _ in 1..DAILY_PUZZLE_COUNT {
+            assert_eq!(challenge.advance(20.0), None);
+        }
+        assert_eq!(challenge.advance(70.5), Some((date, 60.5)));
+        assert!(!challenge.is_active());
+    }
+
+    #[test]
+    fn test_records_keep_best_time_and_round_trip() {
+        // テスト: 最短時間を保持し、テキストとして保存・復元できる
+        let date = DailyDate::new(2024, 3, 7);
+        let mut records = DailyRecords::default();
+        records.record(date, 95.0);
+        records.record(date, 80.0);
+        records.record(date, 120.0);
+        assert_eq!(records.time_for(date), Some(80.0));
+        assert_eq!(records.time_for(DailyDate::new(2024, 3, 8)), None);
+
+        let restored = DailyRecords::parse(&records.to_text());
+        assert_eq!(restored, records);
+    }
+}
@@ -0,0 +1,213 @@
+//! 盤面の難易度評価
+
+use crate::game::{Calculator, Expr, GameNumbers, Operator, Rational, RuleSet, SolutionSpace};
+
+/// 難易度の評価で数える解の数の上限（これ以上はスコアが変わらない）
+pub const DIFFICULTY_SOLUTION_LIMIT: usize = 20;
+
+/// ソルバーの解から求めた盤面の難易度
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difficulty {
+    /// 本質的に異なる解の数（`DIFFICULTY_SOLUTION_LIMIT`個までしか数えない）
+    pub distinct_solutions: usize,
+    /// すべての解が割り算を使う
+    pub requires_division: bool,
+    /// すべての解が途中で分数（整数でない値）を経由する
+    pub requires_fraction: bool,
+    /// すべての解が途中で負の数を経由する
+    pub requires_negative: bool,
+    /// 解の式木の深さの最小値
+    pub min_depth: usize,
+}
+
+impl Difficulty {
+    /// 解の一覧から難易度を求める（解がない場合は`None`）
+    pub fn from_solutions(solutions: &[Expr]) -> Option<Self> {
+        if solutions.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            distinct_solutions: solutions.len(),
+            requires_division: solutions
+                .iter()
+                .all(|expr| expr.contains_operator(Operator::Div)),
+            requires_fraction: solutions
+                .iter()
+                .all(|expr| has_intermediate(expr, |value| !value.is_integer())),
+            requires_negative: solutions
+                .iter()
+                .all(|expr| has_intermediate(expr, Rational::is_negative)),
+            min_depth: solutions.iter().map(Expr::depth).min().unwrap_or(0),
+        })
+    }
+
+    /// 0〜100の難易度スコア
+    pub fn score(&self) -> u32 {
+        // 解が少ないほど難しい
+        let mut score = match self.distinct_solutions {
+            1 => 40,
+            2 => 30,
+            3..=4 => 20,
+            5..=9 => 10,
+            _ => 0,
+        };
+
+        if self.requires_division {
+            score += 15;
+        }
+        if self.requires_fraction {
+            score += 25;
+        }
+        if self.requires_negative {
+            score += 15;
+        }
+        // 深い式しか解にならない場合は少しだけ加算
+        if self.min_depth > 2 {
+            score += 5 * (self.min_depth as u32 - 2);
+        }
+
+        score.min(100)
+    }
+
+    /// 1〜5の星の数
+    pub fn stars(&self) -> u8 {
+        (self.score() / 20 + 1).min(5) as u8
+    }
+
+    /// 星による表示（"***--" など）
+    ///
+    /// Bevyのデフォルトフォントには"★"が含まれないためASCII文字で表す。
+    pub fn star_text(&self) -> String {
+        let stars = self.stars() as usize;
+        format!("{}{}", "*".repeat(stars), "-".repeat(5 - stars))
+    }
+}
+
+impl Calculator {
+    /// 盤面の難易度を求める（解けない盤面の場合は`None`）
+    ///
+    /// 解の数は`DIFFICULTY_SOLUTION_LIMIT`個まで数えるため、6枚の盤面でもすぐに求まる。
+    pub fn difficulty(numbers: &GameNumbers, rules: &RuleSet) -> Option<Difficulty> {
+        SolutionSpace::new(numbers, rules).difficulty(DIFFICULTY_SOLUTION_LIMIT)
+    }
+}
+
+/// 生成する盤面の難易度の範囲（星の数、両端を含む）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DifficultyBand {
+    pub min_stars: u8,
+    pub max_stars: u8,
+}
+
+impl DifficultyBand {
+    pub const fn new(min_stars: u8, max_stars: u8) -> Self {
+        Self {
+            min_stars,
+            max_stars,
+        }
+    }
+
+    /// ステージ番号に応じた難易度の範囲（ステージが進むほど難しくなる）
+    ///
+    /// 星5つの盤面はごく少ないため、最終的な範囲は星4〜5とする。
+    pub fn for_stage(stage: u32) -> Self {
+        match stage {
+            0..=3 => Self::new(1, 1),
+            4..=6 => Self::new(1, 2),
+            7..=9 => Self::new(2, 3),
+            10..=14 => Self::new(3, 4),
+            _ => Self::new(4, 5),
+        }
+    }
+
+    /// 難易度が範囲内か
+    pub fn contains(&self, difficulty: &Difficulty) -> bool {
+        (self.min_stars..=self.max_stars).contains(&difficulty.stars())
+    }
+
+    /// 範囲からどれだけ離れているか（範囲内なら0）
+    pub fn distance(&self, difficulty: &Difficulty) -> u8 {
+        let stars = difficulty.stars();
+        self.min_stars.saturating_sub(stars) + stars.saturating_sub(self.max_stars)
+    }
+}
+
+/// 式の途中結果に条件を満たす値があるか
+fn has_intermediate(expr: &Expr, predicate: impl Fn(&Rational) -> bool) -> bool {
+    expr.intermediate_values()
+        .is_ok_and(|values| values.iter().any(predicate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn difficulty(digits: [u8; 4]) -> Option<Difficulty> {
+        Calculator::difficulty(&GameNumbers::from_digits(digits), &RuleSet::default())
+    }
+
+    #[test]
+    fn test_easy_board() {
+        // テスト: [1, 2, 3, 4] は解が多く、分数も負の数も不要
+        let difficulty = difficulty([1, 2, 3, 4]).unwrap();
+        assert!(difficulty.distinct_solutions > 10);
+        assert!(!difficulty.requires_division);
+        assert!(!difficulty.requires_fraction);
+        assert!(!difficulty.requires_negative);
+        assert_eq!(difficulty.min_depth, 2);
+        assert_eq!(difficulty.stars(), 1);
+    }
+
+    #[test]
+    fn test_hard_board() {
+        // テスト: [1, 1, 5, 8] は 8 / (1 - 1 / 5) のみで分数が必須
+        let difficulty = difficulty([1, 1, 5, 8]).unwrap();
+        assert_eq!(difficulty.distinct_solutions, 1);
+        assert!(difficulty.requires_division);
+        assert!(difficulty.requires_fraction);
+        assert!(!difficulty.requires_negative);
+        assert_eq!(difficulty.min_depth, 3);
+        assert_eq!(difficulty.stars(), 5);
+        assert_eq!(difficulty.star_text(), "*****");
+    }
+
+    #[test]
+    fn test_harder_board_scores_higher() {
+        // テスト: 難しい盤面ほどスコアが高い
+        let easy = difficulty([1, 2, 3, 4]).unwrap();
+        let hard = difficulty([1, 1, 5, 8]).unwrap();
+        assert!(hard.score() > easy.score());
+    }
+
+    #[test]
+    fn test_stage_curve_ramps_up() {
+        // テスト: ステージが進むほど難易度の範囲が上がる
+        assert_eq!(DifficultyBand::for_stage(1), DifficultyBand::new(1, 1));
+        assert_eq!(DifficultyBand::for_stage(50), DifficultyBand::new(4, 5));
+
+        let mut previous = DifficultyBand::for_stage(1);
+        for stage in 2..=30 {
+            let band = DifficultyBand::for_stage(stage);
+            assert!(band.min_stars >= previous.min_stars);
+            assert!(band.max_stars >= previous.max_stars);
+            previous = band;
+        }
+    }
+
+    #[test]
+    fn test_band_contains_and_distance() {
+        // テスト: 範囲の判定と範囲外の距離
+        let hard = difficulty([1, 1, 5, 8]).unwrap();
+        assert!(DifficultyBand::new(4, 5).contains(&hard));
+        assert!(!DifficultyBand::new(1, 2).contains(&hard));
+        assert_eq!(DifficultyBand::new(1, 2).distance(&hard), 3);
+        assert_eq!(DifficultyBand::new(4, 5).distance(&hard), 0);
+    }
+
+    #[test]
+    fn test_unsolvable_board_has_no_difficulty() {
+        // テスト: 解けない盤面は難易度なし
+        assert_eq!(difficulty([0, 0, 0, 0]), None);
+    }
+}
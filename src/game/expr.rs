@@ -0,0 +1,913 @@
+//! 式の構文木
+//!
+//! パーサー・ソルバー・UI・表示で共通して使う式の表現。
+
+use crate::game::{CalculationError, GameNumbers, Operator, Rational};
+use std::fmt;
+
+/// 式の構文木
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Expr {
+    /// 数字（`index`はカードの位置）
+    Literal { index: usize, value: u8 },
+    /// 二項演算
+    Binary {
+        op: Operator,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// 単項演算（階乗・平方根）
+    Unary { op: Operator, operand: Box<Expr> },
+    /// プレイヤーが入力した括弧
+    Paren(Box<Expr>),
+    /// 単項マイナス
+    Neg(Box<Expr>),
+}
+
+/// 式のトークン
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    Literal { index: usize, value: u8 },
+    Operator(Operator),
+    LeftParen,
+    RightParen,
+}
+
+impl Expr {
+    /// 数字の式を作成
+    pub fn literal(index: usize, value: u8) -> Self {
+        Expr::Literal { index, value }
+    }
+
+    /// 二項演算の式を作成
+    pub fn binary(op: Operator, lhs: Expr, rhs: Expr) -> Self {
+        Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        }
+    }
+
+    /// 単項演算の式を作成
+    pub fn unary(op: Operator, operand: Expr) -> Self {
+        Expr::Unary {
+            op,
+            operand: Box::new(operand),
+        }
+    }
+
+    /// 括弧で囲んだ式を作成
+    pub fn paren(inner: Expr) -> Self {
+        Expr::Paren(Box::new(inner))
+    }
+
+    /// 文字列から式を作成（数字のインデックスは出現順）
+    pub fn parse(text: &str) -> Result<Self, CalculationError> {
+        Self::from_tokens(&tokenize(text)?)
+    }
+
+    /// 文字列から式を作成し、各数字を与えられたカードに割り当てる
+    pub fn parse_with_numbers(text: &str, numbers: &GameNumbers) -> Result<Self, CalculationError> {
+        let mut tokens = tokenize(text)?;
+        let mut used = vec![false; numbers.digits.len()];
+
+        for token in tokens.iter_mut() {
+            if let Token::Literal { index, value } = token {
+                let card =
+                    (0..numbers.digits.len()).find(|&i| numbers.digits[i] == *value && !used[i]);
+                match card {
+                    Some(card) => {
+                        used[card] = true;
+                        *index = card;
+                    }
+                    None if numbers.digits.contains(value) => {
+                        return Err(CalculationError::DigitReused(*value));
+                    }
+                    None => return Err(CalculationError::DigitNotAvailable(*value)),
+                }
+            }
+        }
+
+        Self::from_tokens(&tokens)
+    }
+
+    /// トークン列から式を作成
+    pub fn from_tokens(tokens: &[Token]) -> Result<Self, CalculationError> {
+        Parser { tokens, pos: 0 }.parse()
+    }
+
+    /// 式をトークン列に変換
+    pub fn to_tokens(&self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        self.write_tokens(&mut tokens);
+        tokens
+    }
+
+    fn write_tokens(&self, tokens: &mut Vec<Token>) {
+        match self {
+            Expr::Literal { index, value } => tokens.push(Token::Literal {
+                index: *index,
+                value: *value,
+            }),
+            Expr::Binary { op, lhs, rhs } => {
+                let (lhs_paren, rhs_paren) = self.operand_parens();
+                lhs.write_operand(tokens, lhs_paren);
+                tokens.push(Token::Operator(*op));
+                rhs.write_operand(tokens, rhs_paren);
+            }
+            Expr::Unary {
+                op: Operator::Sqrt,
+                operand,
+            } => {
+                // 平方根は常に括弧付きで"sqrt(x)"と表示する
+                tokens.push(Token::Operator(Operator::Sqrt));
+                operand.write_operand(tokens, !matches!(**operand, Expr::Paren(_)));
+            }
+            Expr::Unary { op, operand } => {
+                operand.write_operand(tokens, operand.precedence() < Self::POSTFIX_PRECEDENCE);
+                tokens.push(Token::Operator(*op));
+            }
+            Expr::Paren(inner) => inner.write_operand(tokens, true),
+            Expr::Neg(inner) => {
+                tokens.push(Token::Operator(Operator::Sub));
+                inner.write_operand(tokens, inner.precedence() < Self::NEG_PRECEDENCE);
+            }
+        }
+    }
+
+    /// 必要なら括弧で囲んでトークン列に追加
+    fn write_operand(&self, tokens: &mut Vec<Token>, paren: bool) {
+        if paren {
+            tokens.push(Token::LeftParen);
+        }
+        self.write_tokens(tokens);
+        if paren {
+            tokens.push(Token::RightParen);
+        }
+    }
+
+    const NEG_PRECEDENCE: u8 = 3;
+    const POW_PRECEDENCE: u8 = 4;
+    const CONCAT_PRECEDENCE: u8 = 5;
+    const POSTFIX_PRECEDENCE: u8 = 6;
+
+    /// 演算子の優先順位（大きいほど強く結合する）
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary {
+                op: Operator::Add | Operator::Sub,
+                ..
+            } => 1,
+            Expr::Binary {
+                op: Operator::Pow, ..
+            } => Self::POW_PRECEDENCE,
+            Expr::Binary {
+                op: Operator::Concat,
+                ..
+            } => Self::CONCAT_PRECEDENCE,
+            Expr::Binary { .. } => 2,
+            Expr::Neg(_) => Self::NEG_PRECEDENCE,
+            Expr::Unary {
+                op: Operator::Factorial,
+                ..
+            } => Self::POSTFIX_PRECEDENCE,
+            Expr::Literal { .. } | Expr::Paren(_) | Expr::Unary { .. } => 7,
+        }
+    }
+
+    /// 二項演算の左右の項に括弧が必要かどうか
+    fn operand_parens(&self) -> (bool, bool) {
+        let Expr::Binary { op, lhs, rhs } = self else {
+            return (false, false);
+        };
+        let precedence = self.precedence();
+        match op {
+            // 累乗は右結合で、指数には単項マイナスをそのまま書ける
+            Operator::Pow => (
+                lhs.precedence() <= precedence,
+                rhs.precedence() < Self::NEG_PRECEDENCE,
+            ),
+            // 連結は結合的ではないので右側は同じ優先順位でも括弧が必要
+            Operator::Concat => (
+                lhs.precedence() < precedence,
+                rhs.precedence() <= precedence,
+            ),
+            // 右側は減算・除算のとき同じ優先順位でも括弧が必要
+            _ => (
+                lhs.precedence() < precedence,
+                rhs.precedence() < precedence
+                    || (rhs.precedence() == precedence
+                        && matches!(op, Operator::Sub | Operator::Div)),
+            ),
+        }
+    }
+
+    /// 数字だけを連結した式（連結の項として使える式）かどうか
+    pub fn is_digit_string(&self) -> bool {
+        match self {
+            Expr::Literal { .. } => true,
+            Expr::Binary {
+                op: Operator::Concat,
+                lhs,
+                rhs,
+            } => lhs.is_digit_string() && rhs.is_digit_string(),
+            _ => false,
+        }
+    }
+
+    /// 式を評価
+    pub fn evaluate(&self) -> Result<Rational, CalculationError> {
+        self.collect_intermediates(&mut Vec::new())
+    }
+
+    /// 演算の途中結果を評価順（左右の部分木が先）に列挙
+    pub fn intermediate_values(&self) -> Result<Vec<Rational>, CalculationError> {
+        let mut values = Vec::new();
+        self.collect_intermediates(&mut values)?;
+        Ok(values)
+    }
+
+    fn collect_intermediates(
+        &self,
+        values: &mut Vec<Rational>,
+    ) -> Result<Rational, CalculationError> {
+        let value = match self {
+            Expr::Literal { value, .. } => return Ok(Rational::from(*value)),
+            Expr::Paren(inner) => return inner.collect_intermediates(values),
+            Expr::Binary { op, lhs, rhs } => {
+                // 連結できるのは数字だけ
+                if *op == Operator::Concat && !self.is_digit_string() {
+                    return Err(CalculationError::InvalidOperand(*op));
+                }
+                let lhs = lhs.collect_intermediates(values)?;
+                let rhs = rhs.collect_intermediates(values)?;
+                op.apply_binary(lhs, rhs)?
+            }
+            Expr::Unary { op, operand } => {
+                op.apply_unary(operand.collect_intermediates(values)?)?
+            }
+            Expr::Neg(inner) => inner
+                .collect_intermediates(values)?
+                .checked_neg()
+                .ok_or(CalculationError::Overflow)?,
+        };
+        values.push(value);
+        Ok(value)
+    }
+
+    /// 演算の木の深さ（数字のみは0、括弧は数えない）
+    pub fn depth(&self) -> usize {
+        match self {
+            Expr::Literal { .. } => 0,
+            Expr::Binary { lhs, rhs, .. } => 1 + lhs.depth().max(rhs.depth()),
+            Expr::Paren(inner) => inner.depth(),
+            Expr::Unary { operand: inner, .. } | Expr::Neg(inner) => 1 + inner.depth(),
+        }
+    }
+
+    /// 指定した演算子を含むか
+    pub fn contains_operator(&self, target: Operator) -> bool {
+        self.operators().contains(&target)
+    }
+
+    /// 式中の演算子を出現順に列挙（単項マイナスは含まない）
+    pub fn operators(&self) -> Vec<Operator> {
+        let mut operators = Vec::new();
+        let mut prev: Option<Token> = None;
+        for token in self.to_tokens() {
+            if let Token::Operator(op) = token {
+                let is_neg = op == Operator::Sub && !follows_operand(prev);
+                if !is_neg {
+                    operators.push(op);
+                }
+            }
+            prev = Some(token);
+        }
+        operators
+    }
+
+    /// 式中の数字（カードのインデックスと値）を出現順に列挙
+    pub fn literals(&self) -> Vec<(usize, u8)> {
+        self.to_tokens()
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Literal { index, value } => Some((index, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// すべてのカードをちょうど1回ずつ使っているか
+    pub fn uses_all_numbers(&self, numbers: &GameNumbers) -> bool {
+        let mut indices: Vec<usize> = self.literals().iter().map(|&(index, _)| index).collect();
+        indices.sort();
+        indices == (0..numbers.digits.len()).collect::<Vec<_>>()
+    }
+
+    /// 正規形に変換
+    ///
+    /// 結合的な+・*の連鎖を平坦化し、可換な項を並べ替え、
+    /// 減算・除算は符号付きの項（分子・分母の因子）として展開する。
+    pub fn canonical(&self) -> CanonicalExpr {
+        match self {
+            Expr::Literal { value, .. } => CanonicalExpr::Number(*value),
+            Expr::Paren(inner) => inner.canonical(),
+            Expr::Neg(inner) => {
+                let (positive, negative) = inner.canonical().into_terms();
+                CanonicalExpr::Sum {
+                    positive: negative,
+                    negative: positive,
+                }
+            }
+            Expr::Unary { op, operand } => CanonicalExpr::Apply {
+                op: *op,
+                operands: vec![operand.canonical()],
+            },
+            Expr::Binary { op, lhs, rhs } => {
+                let lhs = lhs.canonical();
+                let rhs = rhs.canonical();
+                match op {
+                    Operator::Add | Operator::Sub => {
+                        let (mut positive, mut negative) = lhs.into_terms();
+                        let (rhs_positive, rhs_negative) = rhs.into_terms();
+                        if *op == Operator::Add {
+                            positive.extend(rhs_positive);
+                            negative.extend(rhs_negative);
+                        } else {
+                            positive.extend(rhs_negative);
+                            negative.extend(rhs_positive);
+                        }
+                        positive.sort();
+                        negative.sort();
+                        CanonicalExpr::Sum { positive, negative }
+                    }
+                    Operator::Mul | Operator::Div => {
+                        let (mut numerator, mut denominator) = lhs.into_factors();
+                        let (rhs_numerator, rhs_denominator) = rhs.into_factors();
+                        if *op == Operator::Mul {
+                            numerator.extend(rhs_numerator);
+                            denominator.extend(rhs_denominator);
+                        } else {
+                            numerator.extend(rhs_denominator);
+                            denominator.extend(rhs_numerator);
+                        }
+                        numerator.sort();
+                        denominator.sort();
+                        CanonicalExpr::Product {
+                            numerator,
+                            denominator,
+                        }
+                    }
+                    _ => CanonicalExpr::Apply {
+                        op: *op,
+                        operands: vec![lhs, rhs],
+                    },
+                }
+            }
+        }
+    }
+
+    /// 正規形が一致する（プレイヤーにとって同じ解である）かどうか
+    pub fn is_equivalent(&self, other: &Expr) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
+
+/// 2つの数値に二項演算子を適用
+pub fn apply_operator(
+    op: Operator,
+    lhs: Rational,
+    rhs: Rational,
+) -> Result<Rational, CalculationError> {
+    op.apply_binary(lhs, rhs)
+}
+
+/// 直前のトークンが項の終わり（数字・閉じ括弧・階乗）かどうか
+fn follows_operand(prev: Option<Token>) -> bool {
+    matches!(
+        prev,
+        Some(Token::Literal { .. } | Token::RightParen | Token::Operator(Operator::Factorial))
+    )
+}
+
+impl fmt::Display for Expr {
+    /// 必要最小限の括弧で表示
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_tokens(f, &self.to_tokens())
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Literal { value, .. } => write!(f, "{}", value),
+            Token::Operator(op) => write!(f, "{}", op),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+        }
+    }
+}
+
+/// トークン列を表示用の文字列に整形
+///
+/// 二項演算子の前後には空白を入れ、括弧の内側、単項マイナスの後ろ、
+/// 階乗の前、平方根と括弧の間は詰めて表示する。
+pub fn write_tokens(f: &mut impl fmt::Write, tokens: &[Token]) -> fmt::Result {
+    let mut prev: Option<Token> = None;
+    let mut prev_is_minus = false;
+    for &token in tokens {
+        // 平方根の直後は括弧のときだけ詰める（"sqrt(9)"、"sqrt 9"）
+        let after_sqrt = prev == Some(Token::Operator(Operator::Sqrt));
+        let joined = matches!(prev, None | Some(Token::LeftParen))
+            || matches!(
+                token,
+                Token::RightParen | Token::Operator(Operator::Factorial)
+            )
+            || prev_is_minus
+            || (after_sqrt && token == Token::LeftParen);
+        if !joined {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", token)?;
+
+        prev_is_minus = token == Token::Operator(Operator::Sub) && !follows_operand(prev);
+        prev = Some(token);
+    }
+    Ok(())
+}
+
+/// 文字列をトークン列に分解（数字のインデックスは出現順）
+pub fn tokenize(text: &str) -> Result<Vec<Token>, CalculationError> {
+    let mut tokens = Vec::new();
+    let mut literal_count = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '0'..='9' => {
+                let token = Token::Literal {
+                    index: literal_count,
+                    value: c as u8 - b'0',
+                };
+                literal_count += 1;
+                token
+            }
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            's' => {
+                // "sqrt"
+                for expected in "qrt".chars() {
+                    if chars.next() != Some(expected) {
+                        return Err(CalculationError::UnknownToken(c));
+                    }
+                }
+                Token::Operator(Operator::Sqrt)
+            }
+            _ => match Operator::from_char(c) {
+                Some(op) => Token::Operator(op),
+                None => return Err(CalculationError::UnknownToken(c)),
+            },
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// 再帰下降パーサー
+///
+/// ```text
+/// expr    := term (('+' | '-') term)*
+/// term    := unary (('*' | '/') unary)*
+/// unary   := '-' unary | power
+/// power   := concat ('^' unary)?
+/// concat  := postfix ('&' postfix)*
+/// postfix := prefix '!'*
+/// prefix  := 'sqrt' prefix | primary
+/// primary := NUMBER | '(' expr ')'
+/// ```
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// 式全体を解析
+    fn parse(&mut self) -> Result<Expr, CalculationError> {
+        if self.tokens.is_empty() {
+            return Err(CalculationError::EmptyExpression);
+        }
+
+        let expr = self.expr()?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(Token::RightParen) => Err(CalculationError::UnbalancedParentheses),
+            Some(token) => Err(CalculationError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    /// 次のトークンが指定した演算子のいずれかなら読み進めて返す
+    fn next_operator(&mut self, operators: &[Operator]) -> Option<Operator> {
+        match self.peek() {
+            Some(Token::Operator(op)) if operators.contains(&op) => {
+                self.next();
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, CalculationError> {
+        let mut expr = self.term()?;
+        while let Some(op) = self.next_operator(&[Operator::Add, Operator::Sub]) {
+            expr = Expr::binary(op, expr, self.term()?);
+        }
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, CalculationError> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.next_operator(&[Operator::Mul, Operator::Div]) {
+            expr = Expr::binary(op, expr, self.unary()?);
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CalculationError> {
+        if self.next_operator(&[Operator::Sub]).is_some() {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, CalculationError> {
+        let base = self.concat()?;
+        if self.next_operator(&[Operator::Pow]).is_some() {
+            return Ok(Expr::binary(Operator::Pow, base, self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn concat(&mut self) -> Result<Expr, CalculationError> {
+        let mut expr = self.postfix()?;
+        while self.next_operator(&[Operator::Concat]).is_some() {
+            expr = Expr::binary(Operator::Concat, expr, self.postfix()?);
+        }
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, CalculationError> {
+        let mut expr = self.prefix()?;
+        while self.next_operator(&[Operator::Factorial]).is_some() {
+            expr = Expr::unary(Operator::Factorial, expr);
+        }
+        Ok(expr)
+    }
+
+    fn prefix(&mut self) -> Result<Expr, CalculationError> {
+        if self.next_operator(&[Operator::Sqrt]).is_some() {
+            return Ok(Expr::unary(Operator::Sqrt, self.prefix()?));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CalculationError> {
+        match self.next() {
+            Some(Token::Literal { index, value }) => Ok(Expr::literal(index, value)),
+            Some(Token::LeftParen) => {
+                let inner = self.expr()?;
+                match self.next() {
+                    Some(Token::RightParen) => Ok(Expr::paren(inner)),
+                    None => Err(CalculationError::UnbalancedParentheses),
+                    Some(token) => Err(CalculationError::UnexpectedToken(token.to_string())),
+                }
+            }
+            Some(Token::RightParen) => Err(CalculationError::UnbalancedParentheses),
+            Some(token) => Err(CalculationError::UnexpectedToken(token.to_string())),
+            None => Err(CalculationError::UnexpectedEnd),
+        }
+    }
+}
+
+/// 式の正規形
+///
+/// 項の並びや括弧の付け方だけが異なる式は同じ正規形になる。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CanonicalExpr {
+    /// 数字
+    Number(u8),
+    /// 和（加算される項と減算される項）
+    Sum {
+        positive: Vec<CanonicalExpr>,
+        negative: Vec<CanonicalExpr>,
+    },
+    /// 積（分子の因子と分母の因子）
+    Product {
+        numerator: Vec<CanonicalExpr>,
+        denominator: Vec<CanonicalExpr>,
+    },
+    /// その他の演算（累乗・連結・階乗・平方根、項の順序を保つ）
+    Apply {
+        op: Operator,
+        operands: Vec<CanonicalExpr>,
+    },
+}
+
+impl CanonicalExpr {
+    /// 和の項に分解
+    fn into_terms(self) -> (Vec<CanonicalExpr>, Vec<CanonicalExpr>) {
+        match self {
+            CanonicalExpr::Sum { positive, negative } => (positive, negative),
+            other => (vec![other], Vec::new()),
+        }
+    }
+
+    /// 積の因子に分解
+    fn into_factors(self) -> (Vec<CanonicalExpr>, Vec<CanonicalExpr>) {
+        match self {
+            CanonicalExpr::Product {
+                numerator,
+                denominator,
+            } => (numerator, denominator),
+            other => (vec![other], Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_respects_precedence() {
+        // テスト: 演算子優先度に従って木が組み立てられる
+        let expr = Expr::parse("1 + 2 * 3").unwrap();
+        assert_eq!(
+            expr,
+            Expr::binary(
+                Operator::Add,
+                Expr::literal(0, 1),
+                Expr::binary(Operator::Mul, Expr::literal(1, 2), Expr::literal(2, 3))
+            )
+        );
+        assert_eq!(expr.evaluate(), Ok(Rational::from_integer(7)));
+    }
+
+    #[test]
+    fn test_parse_keeps_player_parentheses() {
+        // テスト: 入力された括弧はそのまま保持される
+        let expr = Expr::parse("(1+4)*(9-7)").unwrap();
+        assert_eq!(expr.to_string(), "(1 + 4) * (9 - 7)");
+        assert_eq!(expr.evaluate(), Ok(Rational::from_integer(10)));
+
+        let expr = Expr::parse("((2)) - -3").unwrap();
+        assert_eq!(expr.to_string(), "((2)) - -3");
+        assert_eq!(expr.evaluate(), Ok(Rational::from_integer(5)));
+    }
+
+    #[test]
+    fn test_display_uses_minimal_parentheses() {
+        // テスト: 構築した木は必要な箇所だけ括弧で囲まれる
+        let n = Expr::literal;
+        let expr = Expr::binary(
+            Operator::Mul,
+            Expr::binary(Operator::Add, n(0, 1), n(1, 4)),
+            Expr::binary(Operator::Sub, n(2, 9), n(3, 7)),
+        );
+        assert_eq!(expr.to_string(), "(1 + 4) * (9 - 7)");
+
+        let expr = Expr::binary(
+            Operator::Add,
+            Expr::binary(Operator::Add, n(0, 1), n(1, 2)),
+            Expr::binary(Operator::Mul, n(2, 3), n(3, 4)),
+        );
+        assert_eq!(expr.to_string(), "1 + 2 + 3 * 4");
+
+        let expr = Expr::binary(
+            Operator::Sub,
+            n(0, 8),
+            Expr::binary(
+                Operator::Sub,
+                n(1, 1),
+                Expr::binary(Operator::Div, n(2, 1), n(3, 5)),
+            ),
+        );
+        assert_eq!(expr.to_string(), "8 - (1 - 1 / 5)");
+
+        let expr = Expr::binary(
+            Operator::Div,
+            n(0, 8),
+            Expr::binary(Operator::Mul, n(1, 2), n(2, 2)),
+        );
+        assert_eq!(expr.to_string(), "8 / (2 * 2)");
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        // テスト: 表示した文字列を再度解析すると同じ値・同じ解になる
+        let n = Expr::literal;
+        let expr = Expr::binary(
+            Operator::Div,
+            n(0, 8),
+            Expr::binary(
+                Operator::Sub,
+                n(1, 1),
+                Expr::binary(Operator::Div, n(2, 1), n(3, 5)),
+            ),
+        );
+        let text = expr.to_string();
+        assert_eq!(text, "8 / (1 - 1 / 5)");
+
+        let parsed = Expr::parse(&text).unwrap();
+        assert_eq!(parsed.evaluate(), expr.evaluate());
+        assert!(parsed.is_equivalent(&expr));
+        assert_eq!(parsed.to_string(), text);
+    }
+
+    #[test]
+    fn test_tokens_round_trip() {
+        // テスト: トークン列との相互変換
+        let expr = Expr::parse("4 * (3 - 1) + 2").unwrap();
+        assert_eq!(Expr::from_tokens(&expr.to_tokens()), Ok(expr));
+    }
+
+    #[test]
+    fn test_parse_with_numbers_binds_cards() {
+        // テスト: 数字が未使用のカードに順に割り当てられる
+        let numbers = GameNumbers::from_digits([9, 1, 9, 1]);
+        let expr = Expr::parse_with_numbers("9 + 1 * 9 - 9", &numbers);
+        assert_eq!(expr, Err(CalculationError::DigitReused(9)));
+
+        let expr = Expr::parse_with_numbers("(1 / 9 + 1) * 9", &numbers).unwrap();
+        assert_eq!(expr.literals(), vec![(1, 1), (0, 9), (3, 1), (2, 9)]);
+        assert!(expr.uses_all_numbers(&numbers));
+
+        let expr = Expr::parse_with_numbers("9 + 1", &numbers).unwrap();
+        assert!(!expr.uses_all_numbers(&numbers));
+
+        assert_eq!(
+            Expr::parse_with_numbers("9 + 5", &numbers),
+            Err(CalculationError::DigitNotAvailable(5))
+        );
+    }
+
+    #[test]
+    fn test_intermediates_depth_and_operators() {
+        // テスト: 8 / (1 - 1 / 5) の途中結果・深さ・演算子
+        let expr = Expr::parse("8 / (1 - 1 / 5)").unwrap();
+        assert_eq!(
+            expr.intermediate_values().unwrap(),
+            vec![
+                Rational::new(1, 5).unwrap(),
+                Rational::new(4, 5).unwrap(),
+                Rational::from_integer(10),
+            ]
+        );
+        assert_eq!(expr.depth(), 3);
+        assert!(expr.contains_operator(Operator::Div));
+        assert!(!expr.contains_operator(Operator::Mul));
+
+        let expr = Expr::parse("(1 + 4) * (9 - 7)").unwrap();
+        assert_eq!(expr.depth(), 2);
+    }
+
+    #[test]
+    fn test_canonical_flattens_and_sorts() {
+        // テスト: 1+2+3+4、4+3+2+1、(1+2)+(3+4) は同じ解
+        let n = |value| Expr::literal(0, value);
+        let a = Expr::binary(
+            Operator::Add,
+            Expr::binary(Operator::Add, Expr::binary(Operator::Add, n(1), n(2)), n(3)),
+            n(4),
+        );
+        let b = Expr::binary(
+            Operator::Add,
+            Expr::binary(Operator::Add, Expr::binary(Operator::Add, n(4), n(3)), n(2)),
+            n(1),
+        );
+        let c = Expr::binary(
+            Operator::Add,
+            Expr::binary(Operator::Add, n(1), n(2)),
+            Expr::binary(Operator::Add, n(3), n(4)),
+        );
+
+        assert!(a.is_equivalent(&b));
+        assert!(a.is_equivalent(&c));
+    }
+
+    #[test]
+    fn test_canonical_folds_subtraction_and_division() {
+        // テスト: a-(b-c) と a+c-b、a/(b/c) と a*c/b は同じ解
+        let n = |value| Expr::literal(0, value);
+        let sub_nested = Expr::binary(Operator::Sub, n(9), Expr::binary(Operator::Sub, n(2), n(3)));
+        let sub_flat = Expr::binary(Operator::Sub, Expr::binary(Operator::Add, n(9), n(3)), n(2));
+        assert!(sub_nested.is_equivalent(&sub_flat));
+
+        let div_nested = Expr::binary(Operator::Div, n(8), Expr::binary(Operator::Div, n(2), n(5)));
+        let div_flat = Expr::binary(Operator::Div, Expr::binary(Operator::Mul, n(8), n(5)), n(2));
+        assert!(div_nested.is_equivalent(&div_flat));
+
+        // 演算の種類が違えば別の解
+        let product = Expr::binary(Operator::Mul, n(2), n(5));
+        let sum = Expr::binary(Operator::Add, n(2), n(5));
+        assert!(!product.is_equivalent(&sum));
+    }
+
+    #[test]
+    fn test_extended_operators_parse_and_display() {
+        // テスト: 累乗・連結・階乗・平方根の解析と表示
+        let cases = [
+            ("2 ^ 3 + 2", "2 ^ 3 + 2", Rational::from_integer(10)),
+            ("1 & 2 - 2", "1 & 2 - 2", Rational::from_integer(10)),
+            ("3! + 4", "3! + 4", Rational::from_integer(10)),
+            (
+                "sqrt(9) * 3 + 1",
+                "sqrt(9) * 3 + 1",
+                Rational::from_integer(10),
+            ),
+            ("√4 + 8", "sqrt(4) + 8", Rational::from_integer(10)),
+            ("(1 + 2)! + 4", "(1 + 2)! + 4", Rational::from_integer(10)),
+            ("2 ^ 3 ^ 2", "2 ^ 3 ^ 2", Rational::from_integer(512)),
+            ("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2", Rational::from_integer(64)),
+            ("-2 ^ 2", "-2 ^ 2", Rational::from_integer(-4)),
+            ("2 ^ -1", "2 ^ -1", Rational::new(1, 2).unwrap()),
+        ];
+        for (text, display, value) in cases {
+            let expr = Expr::parse(text).unwrap();
+            assert_eq!(expr.to_string(), display, "{}", text);
+            assert_eq!(expr.evaluate(), Ok(value), "{}", text);
+            assert_eq!(Expr::parse(display).unwrap().to_string(), display);
+        }
+    }
+
+    #[test]
+    fn test_built_extended_expressions_display() {
+        // テスト: 構築した木の表示（ソルバーの出力）
+        let n = Expr::literal;
+        let expr = Expr::binary(
+            Operator::Pow,
+            Expr::binary(Operator::Add, n(0, 1), n(1, 2)),
+            n(2, 2),
+        );
+        assert_eq!(expr.to_string(), "(1 + 2) ^ 2");
+
+        let expr = Expr::unary(
+            Operator::Sqrt,
+            Expr::binary(Operator::Add, n(0, 7), n(1, 9)),
+        );
+        assert_eq!(expr.to_string(), "sqrt(7 + 9)");
+        assert_eq!(expr.evaluate(), Ok(Rational::from_integer(4)));
+
+        let expr = Expr::unary(
+            Operator::Factorial,
+            Expr::binary(Operator::Mul, n(0, 1), n(1, 3)),
+        );
+        assert_eq!(expr.to_string(), "(1 * 3)!");
+        assert_eq!(
+            Expr::from_tokens(&expr.to_tokens()).unwrap().evaluate(),
+            Ok(Rational::from_integer(6))
+        );
+    }
+
+    #[test]
+    fn test_concat_requires_digits() {
+        // テスト: 連結できるのは数字だけ
+        let expr = Expr::parse("(1 + 2) & 3").unwrap();
+        assert_eq!(
+            expr.evaluate(),
+            Err(CalculationError::InvalidOperand(Operator::Concat))
+        );
+        assert_eq!(
+            Expr::parse("1 & 2 & 3").unwrap().evaluate(),
+            Ok(Rational::from_integer(123))
+        );
+    }
+
+    #[test]
+    fn test_operators_lists_binary_and_unary() {
+        // テスト: 式中の演算子の列挙（単項マイナスは除く）
+        let expr = Expr::parse("-3! * (2 - sqrt(4))").unwrap();
+        assert_eq!(
+            expr.operators(),
+            vec![
+                Operator::Factorial,
+                Operator::Mul,
+                Operator::Sub,
+                Operator::Sqrt
+            ]
+        );
+    }
+}
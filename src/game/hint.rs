@@ -0,0 +1,105 @@
+//! ソルバーを使ったヒント
+//!
+//! 解の最初の1手（最初に計算する2つの数）を、段階的に詳しく教える。
+
+use crate::game::{Calculator, Expr, GameNumbers, Operator, Rational, RuleSet};
+
+/// ヒントの段階の数
+pub const MAX_HINT_LEVEL: u32 = 3;
+
+/// ヒント1回ごとに減るスコア
+pub const HINT_COST: u32 = 20;
+
+/// 解の最初の1手
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirstStep {
+    /// 左側の数（階乗などが付いている場合はその式）
+    pub lhs: Expr,
+    /// 右側の数
+    pub rhs: Expr,
+    /// 使う演算子
+    pub op: Operator,
+    /// 計算結果
+    pub value: Rational,
+}
+
+impl FirstStep {
+    /// 式の中で最初に計算される二項演算を取り出す
+    ///
+    /// 左の部分木から順に探し、両側に二項演算を含まない最初の演算を返す。
+    pub fn find(expr: &Expr) -> Option<Self> {
+        match expr {
+            Expr::Literal { .. } => None,
+            Expr::Paren(inner) | Expr::Neg(inner) => Self::find(inner),
+            Expr::Unary { operand, .. } => Self::find(operand),
+            Expr::Binary { op, lhs, rhs } => {
+                Self::find(lhs).or_else(|| Self::find(rhs)).or_else(|| {
+                    Some(Self {
+                        lhs: lhs.as_ref().clone(),
+                        rhs: rhs.as_ref().clone(),
+                        op: *op,
+                        value: op
+                            .apply_binary(lhs.evaluate().ok()?, rhs.evaluate().ok()?)
+                            .ok()?,
+                    })
+                })
+            }
+        }
+    }
+
+    /// 段階に応じたヒントの文章（1: 使う数、2: 演算子、3: 計算結果まで）
+    pub fn hint_text(&self, level: u32) -> String {
+        match level {
+            0 => String::new(),
+            1 => format!("Start with {} and {}", self.lhs, self.rhs),
+            2 => format!("Combine {} and {} with {}", self.lhs, self.rhs, self.op),
+            _ => format!("{} {} {} = {}", self.lhs, self.op, self.rhs, self.value),
+        }
+    }
+}
+
+impl Calculator {
+    /// 盤面の解の最初の1手（解がない場合は`None`）
+    ///
+    /// 解は1つ見つかれば十分なので、すべての解は列挙しない。
+    pub fn first_step(numbers: &GameNumbers, rules: &RuleSet) -> Option<FirstStep> {
+        Self::find_solution(numbers, rules)
+            .as_ref()
+            .and_then(FirstStep::find)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_step_of_expression() {
+        // テスト: 最初に計算される2つの数を取り出す
+        let step = FirstStep::find(&Expr::parse("8 / (1 - 1 / 5)").unwrap()).unwrap();
+        assert_eq!(step.lhs.to_string(), "1");
+        assert_eq!(step.rhs.to_string(), "5");
+        assert_eq!(step.op, Operator::Div);
+        assert_eq!(step.value, Rational::new(1, 5).unwrap());
+
+        let step = FirstStep::find(&Expr::parse("3! * 2 - 2").unwrap()).unwrap();
+        assert_eq!(step.lhs.to_string(), "3!");
+        assert_eq!(step.rhs.to_string(), "2");
+        assert_eq!(step.value, Rational::from_integer(12));
+
+        assert_eq!(FirstStep::find(&Expr::parse("7").unwrap()), None);
+    }
+
+    #[test]
+    fn test_hints_get_stronger() {
+        // テスト: 段階ごとに詳しいヒントになる
+        let numbers = GameNumbers::from_digits([1, 1, 9, 9]);
+        let step = Calculator::first_step(&numbers, &RuleSet::default()).unwrap();
+        assert_eq!(step.hint_text(1), "Start with 1 and 9");
+        assert_eq!(step.hint_text(2), "Combine 1 and 9 with /");
+        assert_eq!(step.hint_text(3), "1 / 9 = 1/9");
+
+        let numbers = GameNumbers::from_digits([1, 1, 1, 1]);
+        assert_eq!(Calculator::first_step(&numbers, &RuleSet::default()), None);
+    }
+}
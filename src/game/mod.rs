@@ -1,8 +1,38 @@
+pub mod analysis;
+pub mod answer;
 pub mod calculator;
+pub mod cards;
+pub mod code;
+pub mod daily;
+pub mod difficulty;
+pub mod expr;
+pub mod hint;
 pub mod numbers;
+pub mod operator;
+pub mod rational;
+pub mod rng;
+pub mod rules;
+pub mod solution_space;
 #[cfg(test)]
 mod solvable_numbers_test;
 pub mod state;
+pub mod table;
+pub mod time_attack;
 
+pub use analysis::*;
+pub use answer::*;
 pub use calculator::*;
+pub use cards::*;
+pub use code::*;
+pub use daily::*;
+pub use difficulty::*;
+pub use expr::*;
+pub use hint::*;
 pub use numbers::*;
+pub use operator::*;
+pub use rational::*;
+pub use rng::*;
+pub use rules::*;
+pub use solution_space::*;
+pub use table::*;
+pub use time_attack::*;
@@ -1,88 +1,200 @@
-//! 数字生成とランダム4桁の管理
+//! 数字生成とランダムな数字の管理
 
+use crate::game::{
+    Calculator, DifficultyBand, PuzzleRng, RuleSet, SolvabilityTable, TABLE_MAX_DIGITS, TableEntry,
+};
 use bevy::prelude::*;
 
-/// 4つのランダム数字を表す構造体
+/// ランダムな数字（カード）を表す構造体
 #[derive(Debug, Clone, PartialEq, Resource)]
 pub struct GameNumbers {
-    pub digits: [u8; 4],
+    pub digits: Vec<u8>,
 }
 
 impl GameNumbers {
-    /// 新しいランダムな4桁を生成（必ず解ける組み合わせ）
-    pub fn new() -> Self {
-        use crate::game::Calculator;
-        use std::time::{SystemTime, UNIX_EPOCH};
-
-        // 解ける組み合わせが見つかるまで生成を続ける
-        let mut seed = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos() as u64;
-
-        loop {
-            let candidate = Self::from_seed_with_valid_range(seed);
-            if Calculator::can_make_ten(&candidate) {
-                return candidate;
-            }
-            // 次のシードを試す
-            seed = seed.wrapping_add(1);
+    /// ランダムな4桁を生成（必ず10を作れる組み合わせ）
+    pub fn random() -> Self {
+        Self::generate(&RuleSet::default(), &mut PuzzleRng::from_entropy())
+            .expect("the standard rules always have solvable boards")
+    }
+
+    /// ルールの枚数で、目標の数を作れるランダムな数字を生成
+    ///
+    /// 組み合わせの表がある枚数では、解ける組み合わせから一様に選んで並べ替える。
+    /// 解ける盤面がない場合（表がない場合は一定回数試しても見つからない場合）は`None`。
+    pub fn generate(rules: &RuleSet, rng: &mut PuzzleRng) -> Option<Self> {
+        // 表がない場合の試行回数（ルールによっては解ける盤面がほとんどない）
+        const MAX_ATTEMPTS: usize = 100;
+
+        if let Some(table) = SolvabilityTable::for_rules(rules) {
+            let candidates = Self::candidates(&table);
+            return rng
+                .choose(&candidates)
+                .map(|entry| Self::shuffled(&entry.digits, rng));
         }
+
+        // 表がない場合は解ける組み合わせが見つかるまで生成を続ける
+        (0..MAX_ATTEMPTS).find_map(|_| {
+            let candidate = Self::random_digits(rng, rules.card_count());
+            Calculator::can_make_target(&candidate, rules).then_some(candidate)
+        })
     }
 
-    /// シード値から決定的に4桁を生成（テスト用）
-    pub fn from_seed(mut seed: u64) -> Self {
-        let mut digits = [0u8; 4];
+    /// ルールの枚数で、指定した難易度の範囲に入るランダムな数字を生成
+    ///
+    /// 範囲内の盤面が見つからない場合（枚数や目標によっては存在しない）は、
+    /// 一定回数試した中で最も範囲に近い解ける盤面を返す。
+    /// 解ける盤面が見つからない場合は`None`。
+    pub fn generate_with_difficulty(
+        rules: &RuleSet,
+        band: DifficultyBand,
+        rng: &mut PuzzleRng,
+    ) -> Option<Self> {
+        // 表がある場合の試行回数（難易度は表にキャッシュされる）
+        const MAX_ATTEMPTS: usize = 5000;
+        // 表がない場合の試行回数（毎回難易度を計算するため少なくする）
+        const MAX_UNTABLED_ATTEMPTS: usize = 20;
+        // 6枚以上の場合の試行回数（難易度の計算がさらに重い）
+        const MAX_LARGE_BOARD_ATTEMPTS: usize = 3;
 
-        for i in 0..4 {
-            // 線形合同法による疑似乱数
-            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
-            digits[i] = ((seed / 65536) % 10) as u8;
+        let table = SolvabilityTable::for_rules(rules);
+        let candidates = table.as_deref().map(Self::candidates).unwrap_or_default();
+        let attempts = if !candidates.is_empty() {
+            MAX_ATTEMPTS
+        } else if rules.card_count() <= TABLE_MAX_DIGITS + 1 {
+            MAX_UNTABLED_ATTEMPTS
+        } else {
+            MAX_LARGE_BOARD_ATTEMPTS
+        };
+        let mut best: Option<(u8, Self)> = None;
+
+        for _ in 0..attempts {
+            // 表がある場合は解ける組み合わせから選ぶ（難易度は表にキャッシュされる）
+            let (candidate, difficulty) = match (&table, rng.choose(&candidates)) {
+                (Some(table), Some(entry)) => (
+                    Self::shuffled(&entry.digits, rng),
+                    table.difficulty(entry).cloned(),
+                ),
+                _ => {
+                    // 難易度の計算は重いため、先に解けることを確かめた盤面だけ評価する
+                    let Some(candidate) = Self::generate(rules, rng) else {
+                        break;
+                    };
+                    let difficulty = Calculator::difficulty(&candidate, rules);
+                    (candidate, difficulty)
+                }
+            };
+
+            let Some(difficulty) = difficulty else {
+                continue;
+            };
+            let distance = band.distance(&difficulty);
+            if distance == 0 {
+                return Some(candidate);
+            }
+            if best
+                .as_ref()
+                .is_none_or(|(best_distance, _)| distance < *best_distance)
+            {
+                best = Some((distance, candidate));
+            }
         }
 
+        best.map(|(_, candidate)| candidate)
+            .or_else(|| Self::generate(rules, rng))
+    }
+
+    /// 出題候補（0を含まない解ける組み合わせ）
+    fn candidates(table: &SolvabilityTable) -> Vec<&TableEntry> {
+        table
+            .solvable_entries()
+            .filter(|entry| !entry.digits.contains(&0))
+            .collect()
+    }
+
+    /// 数字をランダムな順に並べた盤面
+    fn shuffled(digits: &[u8], rng: &mut PuzzleRng) -> Self {
+        let mut digits = digits.to_vec();
+        rng.shuffle(&mut digits);
         Self { digits }
     }
 
-    /// シード値から有効範囲（1-9）の4桁を生成
-    fn from_seed_with_valid_range(mut seed: u64) -> Self {
-        let mut digits = [0u8; 4];
+    /// ステージ番号に応じた難易度の数字を生成（解ける盤面が見つからない場合は`None`）
+    pub fn generate_for_stage(rules: &RuleSet, stage: u32, rng: &mut PuzzleRng) -> Option<Self> {
+        Self::generate_with_difficulty(rules, DifficultyBand::for_stage(stage), rng)
+    }
 
-        for i in 0..4 {
-            // 線形合同法による疑似乱数
-            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
-            // 1-9の範囲で生成（0は除外）
-            digits[i] = ((seed / 65536) % 9) as u8 + 1;
-        }
+    /// 日付と番号から決まるデイリーパズルの盤面
+    ///
+    /// 同じ日付・番号からは誰が生成しても同じ盤面になる。
+    /// 番号が進むほど難しくなる。
+    pub fn for_date(year: i32, month: u32, day: u32, index: u32) -> Self {
+        let seed = PuzzleRng::seed_from_parts(&[
+            i64::from(year) as u64,
+            u64::from(month),
+            u64::from(day),
+            u64::from(index),
+        ]);
+        let mut rng = PuzzleRng::from_seed(seed);
+        let stage = 1 + index * 4;
+        Self::generate_for_stage(&RuleSet::default(), stage, &mut rng)
+            .expect("the standard rules always have solvable boards")
+    }
+
+    /// シード値から決定的に4桁（0-9）を生成（テスト用）
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = PuzzleRng::from_seed(seed);
+        let digits = (0..4).map(|_| rng.digit(0..=9)).collect();
 
         Self { digits }
     }
 
-    /// 指定した数字から作成（テスト用）
-    pub fn from_digits(digits: [u8; 4]) -> Self {
+    /// 有効範囲（1-9）の数字を指定した個数生成
+    fn random_digits(rng: &mut PuzzleRng, count: usize) -> Self {
+        let digits = (0..count).map(|_| rng.digit(1..=9)).collect();
+
         Self { digits }
     }
 
+    /// 指定した数字から作成（テスト用）
+    pub fn from_digits(digits: impl Into<Vec<u8>>) -> Self {
+        Self {
+            digits: digits.into(),
+        }
+    }
+
+    /// 数字の個数
+    pub fn len(&self) -> usize {
+        self.digits.len()
+    }
+
+    /// 数字が1つもないか
+    pub fn is_empty(&self) -> bool {
+        self.digits.is_empty()
+    }
+
     /// 各桁が0-9の範囲内かチェック
     pub fn is_valid(&self) -> bool {
-        self.digits.iter().all(|&d| d <= 9)
+        !self.digits.is_empty() && self.digits.iter().all(|&d| d <= 9)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::game::{Operator, OperatorSet, RuleVariant};
 
     #[test]
-    fn test_new_generates_four_digits() {
+    fn test_random_generates_four_digits() {
         // テスト1: 新しい数字生成で4桁が生成されることを確認
-        let numbers = GameNumbers::new();
+        let numbers = GameNumbers::random();
         assert_eq!(numbers.digits.len(), 4);
     }
 
     #[test]
     fn test_digits_are_in_valid_range() {
         // テスト2: 生成された数字が0-9の範囲内であることを確認
-        let numbers = GameNumbers::new();
+        let numbers = GameNumbers::random();
         assert!(numbers.is_valid());
 
         for &digit in &numbers.digits {
@@ -90,6 +202,127 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_generate_respects_target() {
+        // テスト: 目標の数を変えても解ける組み合わせが生成される
+        let rules = RuleSet::with_target(24);
+        for seed in [1, 2, 3] {
+            let numbers = GameNumbers::generate(&rules, &mut PuzzleRng::from_seed(seed)).unwrap();
+            assert!(Calculator::can_make_target(&numbers, &rules));
+        }
+    }
+
+    #[test]
+    fn test_generate_with_difficulty_falls_in_band() {
+        // テスト: 生成された盤面が指定した難易度の範囲に入る
+        let rules = RuleSet::default();
+        for band in [
+            DifficultyBand::new(1, 1),
+            DifficultyBand::new(2, 3),
+            DifficultyBand::new(4, 5),
+        ] {
+            let mut rng = PuzzleRng::from_seed(band.min_stars as u64);
+            for _ in 0..3 {
+                let numbers =
+                    GameNumbers::generate_with_difficulty(&rules, band, &mut rng).unwrap();
+                let difficulty = Calculator::difficulty(&numbers, &rules).unwrap();
+                assert!(
+                    band.contains(&difficulty),
+                    "{:?} has {} stars, expected {:?}",
+                    numbers.digits,
+                    difficulty.stars(),
+                    band
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_for_stage_follows_curve() {
+        // テスト: 後半のステージほど難しい盤面が生成される
+        let rules = RuleSet::default();
+        let mut rng = PuzzleRng::from_seed(7);
+        let early = GameNumbers::generate_for_stage(&rules, 1, &mut rng).unwrap();
+        let late = GameNumbers::generate_for_stage(&rules, 50, &mut rng).unwrap();
+        let early = Calculator::difficulty(&early, &rules).unwrap();
+        let late = Calculator::difficulty(&late, &rules).unwrap();
+        assert!(DifficultyBand::for_stage(1).contains(&early));
+        assert!(DifficultyBand::for_stage(50).contains(&late));
+        assert!(late.stars() > early.stars());
+    }
+
+    #[test]
+    fn test_generate_respects_digit_count() {
+        // テスト: ルールで指定した枚数の数字が生成される
+        for count in [3, 5, 6] {
+            let rules = RuleSet::default().with_digit_count(count);
+            for seed in [1, 2, 3] {
+                let numbers =
+                    GameNumbers::generate(&rules, &mut PuzzleRng::from_seed(seed)).unwrap();
+                assert_eq!(numbers.len(), count);
+                assert!(Calculator::can_make_target(&numbers, &rules));
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_for_special_stages() {
+        // テスト: 特別ルールのステージでもルールの下で解ける盤面が生成される
+        let mut rng = PuzzleRng::from_seed(5);
+        for stage in [5, 10, 15] {
+            let rules = RuleSet::default().with_variant(RuleVariant::for_stage(stage));
+            let numbers = GameNumbers::generate_for_stage(&rules, stage, &mut rng).unwrap();
+            assert_eq!(numbers.len(), rules.card_count());
+            assert!(
+                Calculator::can_make_target(&numbers, &rules),
+                "{:?}",
+                numbers
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_without_table_falls_back() {
+        // テスト: 表のない枚数で届かない難易度を指定しても、試行回数の上限で解ける盤面を返す
+        let rules = RuleSet::default().with_digit_count(5);
+        let mut rng = PuzzleRng::from_seed(3);
+        let numbers =
+            GameNumbers::generate_with_difficulty(&rules, DifficultyBand::new(5, 5), &mut rng)
+                .unwrap();
+        assert_eq!(numbers.len(), 5);
+        assert!(Calculator::can_make_target(&numbers, &rules));
+    }
+
+    #[test]
+    fn test_generate_gives_up_without_solvable_boards() {
+        // テスト: 解ける盤面がない（ほとんどない）ルールでは、試行回数の上限で諦めて`None`を返す
+        let rules = RuleSet::with_target(1000)
+            .with_digit_count(5)
+            .with_operators(OperatorSet::EMPTY.with(Operator::Add));
+        let mut rng = PuzzleRng::from_seed(11);
+        assert_eq!(GameNumbers::generate(&rules, &mut rng), None);
+        assert_eq!(GameNumbers::generate_for_stage(&rules, 20, &mut rng), None);
+
+        // 表がある枚数では、表に解ける組み合わせがなければすぐに`None`を返す
+        let rules = RuleSet::with_target(10_000);
+        assert_eq!(GameNumbers::generate(&rules, &mut rng), None);
+    }
+
+    #[test]
+    fn test_generate_with_integer_intermediates() {
+        // テスト: 途中結果を整数に限るルールでは、整数だけで解ける盤面が生成される
+        let rules = RuleSet::default()
+            .with_non_negative_intermediates(true)
+            .with_integer_intermediates(true);
+        let mut rng = PuzzleRng::from_seed(19);
+        for stage in [1, 12] {
+            let numbers = GameNumbers::generate_for_stage(&rules, stage, &mut rng).unwrap();
+            let solutions = Calculator::all_solutions(&numbers, &rules);
+            assert!(!solutions.is_empty(), "{:?}", numbers);
+            assert!(solutions.iter().all(|expr| rules.check(expr).is_ok()));
+        }
+    }
+
     #[test]
     fn test_from_seed_is_deterministic() {
         // テスト3: 同じシードから同じ数字が生成されることを確認
@@ -100,6 +333,38 @@ mod tests {
         assert_eq!(numbers1, numbers2);
     }
 
+    #[test]
+    fn test_generate_is_deterministic_for_seeded_rng() {
+        // テスト: 同じシードの乱数からは同じ盤面が生成される（リプレイ用）
+        let rules = RuleSet::default();
+        let mut rng1 = PuzzleRng::from_seed(2024);
+        let mut rng2 = PuzzleRng::from_seed(2024);
+        for stage in 1..=5 {
+            assert_eq!(
+                GameNumbers::generate_for_stage(&rules, stage, &mut rng1),
+                GameNumbers::generate_for_stage(&rules, stage, &mut rng2)
+            );
+        }
+    }
+
+    #[test]
+    fn test_for_date_is_deterministic() {
+        // テスト: 同じ日付・番号からは同じ盤面、日付や番号が違えば別の盤面
+        let board = GameNumbers::for_date(2024, 3, 7, 0);
+        assert_eq!(board, GameNumbers::for_date(2024, 3, 7, 0));
+        assert!(Calculator::can_make_ten(&board));
+
+        let boards: Vec<_> = (0..5)
+            .map(|i| GameNumbers::for_date(2024, 3, 7, i))
+            .collect();
+        assert!(boards.iter().any(|other| *other != board));
+        assert!(
+            (1..=5)
+                .map(|day| GameNumbers::for_date(2024, 3, day, 0))
+                .any(|other| other != board)
+        );
+    }
+
     #[test]
     fn test_from_digits_creates_correct_numbers() {
         // テスト4: 指定した数字から正しく作成されることを確認
@@ -122,7 +387,7 @@ mod tests {
         // 注意: from_digitsは現在どんな値でも受け入れるため、
         // より厳密な検証が必要な場合は実装を改善する
         let numbers = GameNumbers {
-            digits: [0, 5, 10, 3],
+            digits: vec![0, 5, 10, 3],
         };
         assert!(!numbers.is_valid());
     }
@@ -0,0 +1,401 @@
+//! 演算子の定義
+//!
+//! 四則演算に加えて、ルールで有効にできる累乗・数字の連結・階乗・平方根を扱う。
+
+use crate::game::{CalculationError, Rational};
+use std::fmt;
+
+/// 演算子が取る項の数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    /// 単項演算（階乗・平方根）
+    Unary,
+    /// 二項演算
+    Binary,
+}
+
+/// 演算子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// 累乗（指数は整数のみ）
+    Pow,
+    /// 数字の連結（1と2で12）
+    Concat,
+    /// 階乗（後置）
+    Factorial,
+    /// 平方根（前置、結果が有理数になる場合のみ）
+    Sqrt,
+}
+
+/// 階乗を計算できる最大の数（i64に収まる範囲）
+const MAX_FACTORIAL: i64 = 20;
+
+impl Operator {
+    /// すべての演算子
+    pub const ALL: [Operator; 8] = [
+        Operator::Add,
+        Operator::Sub,
+        Operator::Mul,
+        Operator::Div,
+        Operator::Pow,
+        Operator::Concat,
+        Operator::Factorial,
+        Operator::Sqrt,
+    ];
+
+    /// 項の数
+    pub fn arity(self) -> Arity {
+        match self {
+            Operator::Factorial | Operator::Sqrt => Arity::Unary,
+            _ => Arity::Binary,
+        }
+    }
+
+    /// 表示用の記号
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Sub => "-",
+            Operator::Mul => "*",
+            Operator::Div => "/",
+            Operator::Pow => "^",
+            Operator::Concat => "&",
+            Operator::Factorial => "!",
+            Operator::Sqrt => "sqrt",
+        }
+    }
+
+    /// 1文字の記号から演算子を取得（平方根は"√"）
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Operator::Add),
+            '-' => Some(Operator::Sub),
+            '*' | '×' => Some(Operator::Mul),
+            '/' | '÷' => Some(Operator::Div),
+            '^' => Some(Operator::Pow),
+            '&' => Some(Operator::Concat),
+            '!' => Some(Operator::Factorial),
+            '√' => Some(Operator::Sqrt),
+            _ => None,
+        }
+    }
+
+    /// 左右を入れ替えても結果が変わらないか
+    pub fn is_commutative(self) -> bool {
+        matches!(self, Operator::Add | Operator::Mul)
+    }
+
+    /// 二項演算を適用
+    ///
+    /// 連結は0以上の整数どうしのみ（先頭の0は残らない）。
+    pub fn apply_binary(self, lhs: Rational, rhs: Rational) -> Result<Rational, CalculationError> {
+        let result = match self {
+            Operator::Add => lhs.checked_add(rhs),
+            Operator::Sub => lhs.checked_sub(rhs),
+            Operator::Mul => lhs.checked_mul(rhs),
+            Operator::Div => {
+                if rhs.is_zero() {
+                    return Err(CalculationError::DivisionByZero);
+                }
+                lhs.checked_div(rhs)
+            }
+            Operator::Pow => {
+                if !rhs.is_integer() {
+                    return Err(CalculationError::InvalidOperand(self));
+                }
+                if lhs.is_zero() && rhs.is_negative() {
+                    return Err(CalculationError::DivisionByZero);
+                }
+                pow(lhs, rhs.numer())
+            }
+            Operator::Concat => {
+                if !lhs.is_integer() || !rhs.is_integer() || lhs.is_negative() || rhs.is_negative()
+                {
+                    return Err(CalculationError::InvalidOperand(self));
+                }
+                let shift = 10i64
+                    .checked_pow(rhs.numer().max(1).ilog10() + 1)
+                    .ok_or(CalculationError::Overflow)?;
+                lhs.numer()
+                    .checked_mul(shift)
+                    .and_then(|value| value.checked_add(rhs.numer()))
+                    .map(Rational::from_integer)
+            }
+            Operator::Factorial | Operator::Sqrt => {
+                return Err(CalculationError::InvalidOperand(self));
+            }
+        };
+        result.ok_or(CalculationError::Overflow)
+    }
+
+    /// 単項演算を適用
+    pub fn apply_unary(self, value: Rational) -> Result<Rational, CalculationError> {
+        match self {
+            Operator::Factorial => {
+                if !value.is_integer() || value.is_negative() {
+                    return Err(CalculationError::InvalidOperand(self));
+                }
+                if value.numer() > MAX_FACTORIAL {
+                    return Err(CalculationError::Overflow);
+                }
+                Ok(Rational::from_integer((2..=value.numer()).product()))
+            }
+            Operator::Sqrt => {
+                if value.is_negative() {
+                    return Err(CalculationError::InvalidOperand(self));
+                }
+                match (exact_sqrt(value.numer()), exact_sqrt(value.denom())) {
+                    (Some(numer), Some(denom)) => {
+                        Rational::new(numer, denom).ok_or(CalculationError::Overflow)
+                    }
+                    _ => Err(CalculationError::InvalidOperand(self)),
+                }
+            }
+            _ => Err(CalculationError::InvalidOperand(self)),
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// 累乗（負の指数は逆数、繰り返し二乗法）
+fn pow(base: Rational, exponent: i64) -> Option<Rational> {
+    let mut result = Rational::ONE;
+    let mut square = base;
+    let mut remaining = exponent.unsigned_abs();
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = result.checked_mul(square)?;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = square.checked_mul(square)?;
+        }
+    }
+
+    if exponent < 0 {
+        Rational::ONE.checked_div(result)
+    } else {
+        Some(result)
+    }
+}
+
+/// 平方数の場合のみ平方根を返す
+fn exact_sqrt(value: i64) -> Option<i64> {
+    let root = (value as f64).sqrt().round() as i64;
+    (root.checked_mul(root)? == value).then_some(root)
+}
+
+/// 有効な演算子の集合
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OperatorSet(u8);
+
+impl OperatorSet {
+    /// 四則演算
+    pub const BASIC: Self = Self(0b1111);
+    /// すべての演算子
+    pub const ALL: Self = Self(0xff);
+    /// 空の集合
+    pub const EMPTY: Self = Self(0);
+
+    fn bit(op: Operator) -> u8 {
+        1 << Operator::ALL.iter().position(|&o| o == op).unwrap()
+    }
+
+    /// ビット列から作成（ビットの順は`Operator::ALL`の順）
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// ビット列
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// 演算子を含むか
+    pub fn contains(self, op: Operator) -> bool {
+        self.0 & Self::bit(op) != 0
+    }
+
+    /// 演算子を追加した集合
+    pub fn with(self, op: Operator) -> Self {
+        Self(self.0 | Self::bit(op))
+    }
+
+    /// 演算子を取り除いた集合
+    pub fn without(self, op: Operator) -> Self {
+        Self(self.0 & !Self::bit(op))
+    }
+
+    /// 両方の集合に含まれる演算子
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// どちらかの集合に含まれる演算子
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// 共通の演算子がないか
+    pub fn is_disjoint(self, other: Self) -> bool {
+        self.0 & other.0 == 0
+    }
+
+    /// 含まれる演算子の数
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// 空の集合か
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// 含まれる演算子（`Operator::ALL`の順）
+    pub fn iter(self) -> impl Iterator<Item = Operator> {
+        Operator::ALL
+            .into_iter()
+            .filter(move |&op| self.contains(op))
+    }
+
+    /// 含まれる二項演算子
+    pub fn binary(self) -> impl Iterator<Item = Operator> {
+        self.iter().filter(|op| op.arity() == Arity::Binary)
+    }
+
+    /// 含まれる単項演算子
+    pub fn unary(self) -> impl Iterator<Item = Operator> {
+        self.iter().filter(|op| op.arity() == Arity::Unary)
+    }
+}
+
+impl Default for OperatorSet {
+    fn default() -> Self {
+        Self::BASIC
+    }
+}
+
+impl FromIterator<Operator> for OperatorSet {
+    fn from_iter<I: IntoIterator<Item = Operator>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::EMPTY, |set, op| set.with(op))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i64) -> Rational {
+        Rational::from_integer(value)
+    }
+
+    #[test]
+    fn test_arity_and_symbols() {
+        // テスト: 項の数と記号の対応
+        assert_eq!(Operator::Add.arity(), Arity::Binary);
+        assert_eq!(Operator::Concat.arity(), Arity::Binary);
+        assert_eq!(Operator::Factorial.arity(), Arity::Unary);
+        assert_eq!(Operator::Sqrt.arity(), Arity::Unary);
+        for op in Operator::ALL {
+            if op != Operator::Sqrt {
+                let c = op.symbol().chars().next().unwrap();
+                assert_eq!(Operator::from_char(c), Some(op));
+            }
+        }
+        assert_eq!(Operator::from_char('√'), Some(Operator::Sqrt));
+    }
+
+    #[test]
+    fn test_power() {
+        // テスト: 累乗（負の指数は逆数、指数は整数のみ）
+        assert_eq!(Operator::Pow.apply_binary(int(2), int(3)), Ok(int(8)));
+        assert_eq!(
+            Operator::Pow.apply_binary(int(2), int(-2)),
+            Ok(Rational::new(1, 4).unwrap())
+        );
+        assert_eq!(Operator::Pow.apply_binary(int(5), int(0)), Ok(int(1)));
+        assert_eq!(
+            Operator::Pow.apply_binary(int(4), Rational::new(1, 2).unwrap()),
+            Err(CalculationError::InvalidOperand(Operator::Pow))
+        );
+        assert_eq!(
+            Operator::Pow.apply_binary(int(0), int(-1)),
+            Err(CalculationError::DivisionByZero)
+        );
+        assert_eq!(
+            Operator::Pow.apply_binary(int(9), int(99)),
+            Err(CalculationError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_concat() {
+        // テスト: 数字の連結
+        assert_eq!(Operator::Concat.apply_binary(int(1), int(2)), Ok(int(12)));
+        assert_eq!(Operator::Concat.apply_binary(int(12), int(3)), Ok(int(123)));
+        assert_eq!(Operator::Concat.apply_binary(int(1), int(0)), Ok(int(10)));
+        assert_eq!(
+            Operator::Concat.apply_binary(int(1), Rational::new(1, 2).unwrap()),
+            Err(CalculationError::InvalidOperand(Operator::Concat))
+        );
+    }
+
+    #[test]
+    fn test_factorial_and_sqrt() {
+        // テスト: 階乗と平方根
+        assert_eq!(Operator::Factorial.apply_unary(int(0)), Ok(int(1)));
+        assert_eq!(Operator::Factorial.apply_unary(int(4)), Ok(int(24)));
+        assert_eq!(
+            Operator::Factorial.apply_unary(int(21)),
+            Err(CalculationError::Overflow)
+        );
+        assert_eq!(
+            Operator::Factorial.apply_unary(int(-1)),
+            Err(CalculationError::InvalidOperand(Operator::Factorial))
+        );
+
+        assert_eq!(Operator::Sqrt.apply_unary(int(9)), Ok(int(3)));
+        assert_eq!(
+            Operator::Sqrt.apply_unary(Rational::new(4, 9).unwrap()),
+            Ok(Rational::new(2, 3).unwrap())
+        );
+        assert_eq!(
+            Operator::Sqrt.apply_unary(int(2)),
+            Err(CalculationError::InvalidOperand(Operator::Sqrt))
+        );
+    }
+
+    #[test]
+    fn test_operator_set() {
+        // テスト: 演算子の集合
+        let set = OperatorSet::BASIC;
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![Operator::Add, Operator::Sub, Operator::Mul, Operator::Div]
+        );
+        assert_eq!(set.unary().count(), 0);
+
+        let set = set.with(Operator::Sqrt).without(Operator::Div);
+        assert!(set.contains(Operator::Sqrt));
+        assert!(!set.contains(Operator::Div));
+        assert_eq!(set.unary().collect::<Vec<_>>(), vec![Operator::Sqrt]);
+        assert_eq!(OperatorSet::from_bits(set.bits()), set);
+        assert_eq!(
+            [Operator::Add, Operator::Mul]
+                .into_iter()
+                .collect::<OperatorSet>(),
+            OperatorSet::BASIC
+                .without(Operator::Sub)
+                .without(Operator::Div)
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! 有理数による厳密な計算
+
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// 既約分数で表現された有理数
+///
+/// 分母は常に正で、分子と分母は互いに素に正規化される。
+/// 演算はオーバーフロー時に`None`を返す。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct Rational {
+    numer: i64,
+    denom: i64,
+}
+
+impl Rational {
+    pub const ZERO: Self = Self { numer: 0, denom: 1 };
+    pub const ONE: Self = Self { numer: 1, denom: 1 };
+
+    /// 分子と分母から作成（分母が0の場合やオーバーフロー時は`None`）
+    pub fn new(numer: i64, denom: i64) -> Option<Self> {
+        Self::from_i128(numer as i128, denom as i128)
+    }
+
+    /// 整数から作成
+    pub const fn from_integer(value: i64) -> Self {
+        Self {
+            numer: value,
+            denom: 1,
+        }
+    }
+
+    /// 分子
+    pub fn numer(&self) -> i64 {
+        self.numer
+    }
+
+    /// 分母（常に正）
+    pub fn denom(&self) -> i64 {
+        self.denom
+    }
+
+    /// 整数かどうか
+    pub fn is_integer(&self) -> bool {
+        self.denom == 1
+    }
+
+    /// 0かどうか
+    pub fn is_zero(&self) -> bool {
+        self.numer == 0
+    }
+
+    /// 負の数かどうか
+    pub fn is_negative(&self) -> bool {
+        self.numer < 0
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        Self::from_i128(
+            self.numer as i128 * rhs.denom as i128 + rhs.numer as i128 * self.denom as i128,
+            self.denom as i128 * rhs.denom as i128,
+        )
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        Self::from_i128(
+            self.numer as i128 * rhs.denom as i128 - rhs.numer as i128 * self.denom as i128,
+            self.denom as i128 * rhs.denom as i128,
+        )
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Self::from_i128(
+            self.numer as i128 * rhs.numer as i128,
+            self.denom as i128 * rhs.denom as i128,
+        )
+    }
+
+    /// 除算（ゼロ除算の場合も`None`）
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        Self::from_i128(
+            self.numer as i128 * rhs.denom as i128,
+            self.denom as i128 * rhs.numer as i128,
+        )
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Self {
+            numer: self.numer.checked_neg()?,
+            denom: self.denom,
+        })
+    }
+
+    /// 浮動小数点数に変換（表示用）
+    pub fn to_f64(self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    /// 正規化してi64に収まるか確認
+    fn from_i128(numer: i128, denom: i128) -> Option<Self> {
+        if denom == 0 {
+            return None;
+        }
+
+        let divisor = gcd(numer.unsigned_abs(), denom.unsigned_abs()) as i128;
+        let sign = if denom < 0 { -1 } else { 1 };
+
+        Some(Self {
+            numer: i64::try_from(sign * numer / divisor).ok()?,
+            denom: i64::try_from(sign * denom / divisor).ok()?,
+        })
+    }
+}
+
+/// 最大公約数（ユークリッドの互除法）
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Self::from_integer(value)
+    }
+}
+
+impl From<u8> for Rational {
+    fn from(value: u8) -> Self {
+        Self::from_integer(value as i64)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // 分母は常に正なので交差乗算で比較できる
+        (self.numer as i128 * other.denom as i128).cmp(&(other.numer as i128 * self.denom as i128))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_normalizes() {
+        // テスト: 約分と符号の正規化
+        let r = Rational::new(6, -4).unwrap();
+        assert_eq!(r.numer(), -3);
+        assert_eq!(r.denom(), 2);
+        assert_eq!(Rational::new(0, -5), Some(Rational::ZERO));
+        assert_eq!(Rational::new(1, 0), None);
+    }
+
+    #[test]
+    fn test_arithmetic_is_exact() {
+        // テスト: 8 / 3 * 3 が正確に8になる
+        let eight = Rational::from(8u8);
+        let three = Rational::from(3u8);
+        let result = eight
+            .checked_div(three)
+            .unwrap()
+            .checked_mul(three)
+            .unwrap();
+        assert_eq!(result, eight);
+        assert!(result.is_integer());
+    }
+
+    #[test]
+    fn test_classic_fraction_make_ten() {
+        // テスト: 8 / (1 - 1 / 5) = 10
+        let one = Rational::ONE;
+        let five = Rational::from(5u8);
+        let eight = Rational::from(8u8);
+        let denom = one.checked_sub(one.checked_div(five).unwrap()).unwrap();
+        assert_eq!(eight.checked_div(denom), Some(Rational::from(10i64)));
+    }
+
+    #[test]
+    fn test_division_by_zero_and_overflow() {
+        // テスト: ゼロ除算とオーバーフローはNone
+        assert_eq!(Rational::ONE.checked_div(Rational::ZERO), None);
+        let max = Rational::from(i64::MAX);
+        assert_eq!(max.checked_add(Rational::ONE), None);
+        assert_eq!(Rational::from(i64::MIN).checked_neg(), None);
+    }
+
+    #[test]
+    fn test_ordering_and_display() {
+        // テスト: 比較と表示
+        let half = Rational::new(1, 2).unwrap();
+        let third = Rational::new(1, 3).unwrap();
+        assert!(third < half);
+        assert!(Rational::new(-1, 2).unwrap() < Rational::ZERO);
+        assert_eq!(half.to_string(), "1/2");
+        assert_eq!(Rational::new(-4, 2).unwrap().to_string(), "-2");
+    }
+}
@@ -0,0 +1,180 @@
+//! パズル生成用の疑似乱数生成器
+
+use bevy::prelude::*;
+use std::ops::RangeInclusive;
+
+/// シード指定可能なパズル生成用の乱数（xoshiro256**）
+///
+/// 同じシードからは常に同じ乱数列が得られるため、
+/// リプレイやテストで同じ盤面を再現できる。
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct PuzzleRng {
+    state: [u64; 4],
+}
+
+/// SplitMix64の1ステップ（状態を進めて64ビットの値を返す）
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl PuzzleRng {
+    /// シード値から作成
+    pub fn from_seed(seed: u64) -> Self {
+        // SplitMix64で内部状態を初期化（全ビット0の状態を避ける）
+        let mut sm = seed;
+        let mut next = || splitmix64(&mut sm);
+
+        Self {
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    /// 複数の値を混ぜ合わせて1つのシード値にする
+    ///
+    /// 値ごとにSplitMix64でかき混ぜるため、ビットを詰めて並べる方法と違い
+    /// 値の範囲や符号によって別の組み合わせと衝突することがない。
+    pub fn seed_from_parts(parts: &[u64]) -> u64 {
+        parts.iter().fold(0, |acc, &part| {
+            let mut state = acc ^ part;
+            splitmix64(&mut state)
+        })
+    }
+
+    /// 現在時刻をシードにして作成
+    pub fn from_entropy() -> Self {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        Self::from_seed(seed)
+    }
+
+    /// 次の64ビットの乱数
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        result
+    }
+
+    /// 0以上`bound`未満の一様な乱数（`bound`は1以上）
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "bound must be positive");
+
+        // 偏りが出る端数の範囲を棄却する
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+
+    /// 指定範囲（両端を含む）の一様な数字
+    pub fn digit(&mut self, range: RangeInclusive<u8>) -> u8 {
+        let (start, end) = range.into_inner();
+        start + self.below((end - start) as u64 + 1) as u8
+    }
+
+    /// 要素をランダムに並べ替える（Fisher-Yates）
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    /// 要素を1つ一様に選ぶ（空の場合は`None`）
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u64) as usize)
+    }
+}
+
+impl Default for PuzzleRng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        // テスト: 同じシードからは同じ乱数列が得られる
+        let mut a = PuzzleRng::from_seed(42);
+        let mut b = PuzzleRng::from_seed(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        let mut c = PuzzleRng::from_seed(43);
+        assert_ne!(PuzzleRng::from_seed(42).next_u64(), c.next_u64());
+    }
+
+    #[test]
+    fn test_zero_seed_is_usable() {
+        // テスト: シード0でも0以外の値が出る
+        let mut rng = PuzzleRng::from_seed(0);
+        assert!((0..10).any(|_| rng.next_u64() != 0));
+    }
+
+    #[test]
+    fn test_seed_from_parts_does_not_collide() {
+        // テスト: ビットを詰めると重なる組み合わせでも別のシードになる
+        let seed = |year: i32, month: u64, day: u64, index: u64| {
+            PuzzleRng::seed_from_parts(&[i64::from(year) as u64, month, day, index])
+        };
+        assert_eq!(seed(2024, 3, 7, 0), seed(2024, 3, 7, 0));
+        // 番号が16ビットを超えても日付の欄とぶつからない
+        assert_ne!(seed(2024, 3, 7, 1 << 16), seed(2024, 3, 8, 0));
+        // 負の年が他の欄を上書きしない
+        assert_ne!(seed(-1, 3, 7, 0), seed(-1, 4, 7, 0));
+        assert_ne!(seed(-1, 3, 7, 0), seed(2024, 3, 7, 0));
+    }
+
+    #[test]
+    fn test_shuffle_keeps_elements() {
+        // テスト: 並べ替えても要素は変わらない
+        let mut rng = PuzzleRng::from_seed(1);
+        let mut items = [1, 2, 3, 4, 5, 6];
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+    }
+
+    #[test]
+    fn test_digits_are_in_range_and_roughly_uniform() {
+        // テスト: 1〜9の数字がほぼ均等に出る
+        let mut rng = PuzzleRng::from_seed(12345);
+        let mut counts = [0usize; 10];
+        for _ in 0..9000 {
+            counts[rng.digit(1..=9) as usize] += 1;
+        }
+
+        assert_eq!(counts[0], 0);
+        for &count in &counts[1..] {
+            assert!((800..1200).contains(&count), "counts: {:?}", counts);
+        }
+    }
+}
@@ -0,0 +1,549 @@
+//! ゲームのルール設定
+
+use crate::game::{Expr, Operator, OperatorSet, Rational};
+use bevy::prelude::*;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// 演算子の使い方を制限する特別ルール
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RuleVariant {
+    /// 制限なし
+    #[default]
+    Standard,
+    /// 割り算禁止
+    NoDivision,
+    /// 足し算と掛け算のみ
+    OnlyAddMul,
+    /// 使用できる演算子をそれぞれちょうど1回ずつ使う
+    EachOperatorOnce,
+}
+
+impl RuleVariant {
+    /// 特別ルールの順番
+    pub const SPECIALS: [RuleVariant; 3] = [
+        RuleVariant::NoDivision,
+        RuleVariant::OnlyAddMul,
+        RuleVariant::EachOperatorOnce,
+    ];
+
+    /// 特別ルールになるステージの間隔
+    pub const SPECIAL_STAGE_INTERVAL: u32 = 5;
+
+    /// パズルコード用の1バイト表現
+    pub fn to_byte(self) -> u8 {
+        match self {
+            RuleVariant::Standard => 0,
+            RuleVariant::NoDivision => 1,
+            RuleVariant::OnlyAddMul => 2,
+            RuleVariant::EachOperatorOnce => 3,
+        }
+    }
+
+    /// パズルコードの1バイトから復元（不明な値は`None`）
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(RuleVariant::Standard),
+            1 => Some(RuleVariant::NoDivision),
+            2 => Some(RuleVariant::OnlyAddMul),
+            3 => Some(RuleVariant::EachOperatorOnce),
+            _ => None,
+        }
+    }
+
+    /// ステージ番号に応じたルール（5ステージごとに特別ルールを順に出題）
+    pub fn for_stage(stage: u32) -> Self {
+        if stage == 0 || !stage.is_multiple_of(Self::SPECIAL_STAGE_INTERVAL) {
+            return RuleVariant::Standard;
+        }
+        let round = (stage / Self::SPECIAL_STAGE_INTERVAL - 1) as usize;
+        Self::SPECIALS[round % Self::SPECIALS.len()]
+    }
+}
+
+/// 答えの式がルールに違反している理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleViolation {
+    /// 使用できない演算子を使った
+    ForbiddenOperator(Operator),
+    /// 1回しか使えない演算子を2回以上使った
+    OperatorReused(Operator),
+    /// 使わなければならない演算子を使っていない
+    OperatorUnused(Operator),
+    /// 途中の計算結果が負の数になった
+    NegativeIntermediate(Rational),
+    /// 途中の計算結果が整数でなくなった
+    FractionalIntermediate(Rational),
+}
+
+impl fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleViolation::ForbiddenOperator(op) => write!(f, "'{}' is not allowed", op),
+            RuleViolation::OperatorReused(op) => write!(f, "'{}' can only be used once", op),
+            RuleViolation::OperatorUnused(op) => write!(f, "'{}' must be used", op),
+            RuleViolation::NegativeIntermediate(value) => {
+                write!(f, "intermediate {} is negative", value)
+            }
+            RuleViolation::FractionalIntermediate(value) => {
+                write!(f, "intermediate {} is not an integer", value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleViolation {}
+
+/// ステージのルールを表すリソース
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Resource)]
+pub struct RuleSet {
+    /// 作るべき数
+    pub target: i64,
+    /// カード（数字）の枚数
+    pub digit_count: usize,
+    /// 有効な演算子
+    pub operators: OperatorSet,
+    /// 演算子の使い方の特別ルール
+    pub variant: RuleVariant,
+    /// 途中の計算結果を0以上に限る
+    pub non_negative_intermediates: bool,
+    /// 途中の計算結果を整数に限る
+    pub integer_intermediates: bool,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            target: 10,
+            digit_count: 4,
+            operators: OperatorSet::BASIC,
+            variant: RuleVariant::Standard,
+            non_negative_intermediates: false,
+            integer_intermediates: false,
+        }
+    }
+}
+
+impl RuleSet {
+    /// ゲームで扱えるカードの枚数
+    pub const DIGIT_COUNTS: RangeInclusive<usize> = 3..=6;
+
+    /// ステージ番号に応じたカードの枚数（序盤は3枚、終盤は5枚、さらに進むと6枚）
+    pub fn digit_count_for_stage(stage: u32) -> usize {
+        match stage {
+            0..=2 => 3,
+            3..=19 => 4,
+            20..=29 => 5,
+            _ => 6,
+        }
+    }
+
+    /// 目標の数を指定して作成
+    pub fn with_target(target: i64) -> Self {
+        Self {
+            target,
+            ..default()
+        }
+    }
+
+    /// カードの枚数を変更
+    pub fn with_digit_count(mut self, digit_count: usize) -> Self {
+        self.digit_count = digit_count;
+        self
+    }
+
+    /// 有効な演算子を変更
+    pub fn with_operators(mut self, operators: OperatorSet) -> Self {
+        self.operators = operators;
+        self
+    }
+
+    /// 演算子を追加で有効にする
+    pub fn with_operator(mut self, op: Operator) -> Self {
+        self.operators = self.operators.with(op);
+        self
+    }
+
+    /// 特別ルールを変更
+    pub fn with_variant(mut self, variant: RuleVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// 途中の計算結果を0以上に限るかを変更
+    pub fn with_non_negative_intermediates(mut self, enabled: bool) -> Self {
+        self.non_negative_intermediates = enabled;
+        self
+    }
+
+    /// 途中の計算結果を整数に限るかを変更
+    pub fn with_integer_intermediates(mut self, enabled: bool) -> Self {
+        self.integer_intermediates = enabled;
+        self
+    }
+
+    /// 途中の計算結果として認められる値か
+    pub fn check_intermediate(&self, value: Rational) -> Result<(), RuleViolation> {
+        if self.non_negative_intermediates && value.is_negative() {
+            return Err(RuleViolation::NegativeIntermediate(value));
+        }
+        if self.integer_intermediates && !value.is_integer() {
+            return Err(RuleViolation::FractionalIntermediate(value));
+        }
+        Ok(())
+    }
+
+    /// 特別ルールを考慮して実際に使用できる演算子
+    pub fn allowed_operators(&self) -> OperatorSet {
+        match self.variant {
+            RuleVariant::Standard | RuleVariant::EachOperatorOnce => self.operators,
+            RuleVariant::NoDivision => self.operators.without(Operator::Div),
+            RuleVariant::OnlyAddMul => [Operator::Add, Operator::Mul].into_iter().collect(),
+        }
+    }
+
+    /// 演算子をそれぞれ1回ずつ使うルールか
+    pub fn each_operator_once(&self) -> bool {
+        self.variant == RuleVariant::EachOperatorOnce
+    }
+
+    /// 出題するカードの枚数
+    ///
+    /// 演算子を1回ずつ使うルールでは、二項演算子の数より1枚多いカードが必要になる。
+    pub fn card_count(&self) -> usize {
+        if self.each_operator_once() {
+            self.allowed_operators().binary().count() + 1
+        } else {
+            self.digit_count
+        }
+    }
+
+    /// すでに使った演算子に続けて、演算子を使えるか
+    pub fn can_use_operator(&self, op: Operator, used: &[Operator]) -> bool {
+        self.allowed_operators().contains(op) && !(self.each_operator_once() && used.contains(&op))
+    }
+
+    /// 答えの式がルールを守っているか検証
+    pub fn check(&self, expr: &Expr) -> Result<(), RuleViolation> {
+        let allowed = self.allowed_operators();
+        let used = expr.operators();
+        for (i, &op) in used.iter().enumerate() {
+            if !allowed.contains(op) {
+                return Err(RuleViolation::ForbiddenOperator(op));
+            }
+            if self.each_operator_once() && used[..i].contains(&op) {
+                return Err(RuleViolation::OperatorReused(op));
+            }
+        }
+        if self.each_operator_once()
+            && let Some(op) = allowed.iter().find(|op| !used.contains(op))
+        {
+            return Err(RuleViolation::OperatorUnused(op));
+        }
+        if self.non_negative_intermediates || self.integer_intermediates {
+            for value in expr.intermediate_values().unwrap_or_default() {
+                self.check_intermediate(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 画面に表示するルールの説明
+    pub fn description(&self) -> String {
+        let symbols: Vec<&str> = self
+            .allowed_operators()
+            .iter()
+            .map(Operator::symbol)
+            .collect();
+        let symbols = symbols.join(" ");
+        let mut description = match self.variant {
+            RuleVariant::Standard => format!("Operators: {}", symbols),
+            RuleVariant::NoDivision => format!("No division: {}", symbols),
+            RuleVariant::OnlyAddMul => format!("Only {}", symbols),
+            RuleVariant::EachOperatorOnce => format!("Use each once: {}", symbols),
+        };
+        if self.non_negative_intermediates {
+            description.push_str(", no negatives");
+        }
+        if self.integer_intermediates {
+            description.push_str(", integers only");
+        }
+        description
+    }
+
+    /// 目標の数を有理数として取得
+    pub fn target_value(&self) -> Rational {
+        Rational::from_integer(self.target)
+    }
+
+    /// ゲームのタイトル（"Make 10" など）
+    pub fn title(&self) -> String {
+        format!("Make {}", self.target)
+    }
+}
+
+/// メインメニューで選ぶ追加ルール（ハウスルール）
+///
+/// ステージごとのルールはこの設定から作るため、ステージが進んでも設定は変わらない。
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct HouseRules {
+    /// 有効な演算子
+    pub operators: OperatorSet,
+    /// 途中の計算結果を0以上に限る
+    pub non_negative_intermediates: bool,
+    /// 途中の計算結果を整数に限る
+    pub integer_intermediates: bool,
+}
+
+impl Default for HouseRules {
+    fn default() -> Self {
+        Self {
+            operators: OperatorSet::BASIC,
+            non_negative_intermediates: false,
+            integer_intermediates: false,
+        }
+    }
+}
+
+impl HouseRules {
+    /// 四則演算に加えて有効にできる演算子
+    pub const OPTIONAL_OPERATORS: [Operator; 4] = [
+        Operator::Pow,
+        Operator::Concat,
+        Operator::Factorial,
+        Operator::Sqrt,
+    ];
+
+    /// 演算子の有効・無効を切り替える
+    pub fn toggle_operator(&mut self, op: Operator) {
+        self.operators = if self.operators.contains(op) {
+            self.operators.without(op)
+        } else {
+            self.operators.with(op)
+        };
+    }
+
+    /// ステージのルール
+    ///
+    /// 演算子を1回ずつ使うステージは、カードが増えすぎないよう四則演算のみで出題する。
+    /// 5枚以上のカードは四則演算の標準ルールのみとし、特別ルールや拡張演算子では4枚までにする
+    /// （解ける盤面が少なかったり解き方が多かったりして、盤面の生成に時間がかかる）。
+    pub fn rules_for_stage(&self, stage: u32) -> RuleSet {
+        let variant = RuleVariant::for_stage(stage);
+        let operators = if variant == RuleVariant::EachOperatorOnce {
+            OperatorSet::BASIC
+        } else {
+            self.operators
+        };
+        let mut digit_count = RuleSet::digit_count_for_stage(stage);
+        if variant != RuleVariant::Standard || operators != OperatorSet::BASIC {
+            digit_count = digit_count.min(RuleSet::default().digit_count);
+        }
+        RuleSet::default()
+            .with_digit_count(digit_count)
+            .with_operators(operators)
+            .with_variant(variant)
+            .with_non_negative_intermediates(self.non_negative_intermediates)
+            .with_integer_intermediates(self.integer_intermediates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_target_is_ten() {
+        // テスト: デフォルトの目標は10
+        let rules = RuleSet::default();
+        assert_eq!(rules.target, 10);
+        assert_eq!(rules.digit_count, 4);
+        assert_eq!(rules.target_value(), Rational::from_integer(10));
+        assert_eq!(rules.title(), "Make 10");
+        assert_eq!(rules.operators, OperatorSet::BASIC);
+        assert_eq!(rules.variant, RuleVariant::Standard);
+        assert_eq!(rules.description(), "Operators: + - * /");
+    }
+
+    #[test]
+    fn test_custom_target() {
+        // テスト: 目標の数を変更できる
+        let rules = RuleSet::with_target(24);
+        assert_eq!(rules.target_value(), Rational::from_integer(24));
+        assert_eq!(rules.title(), "Make 24");
+    }
+
+    #[test]
+    fn test_enable_extended_operators() {
+        // テスト: 拡張演算子をルールで有効にする
+        let rules = RuleSet::default()
+            .with_operator(Operator::Pow)
+            .with_operator(Operator::Factorial);
+        assert!(rules.operators.contains(Operator::Add));
+        assert!(rules.operators.contains(Operator::Pow));
+        assert!(rules.operators.contains(Operator::Factorial));
+        assert!(!rules.operators.contains(Operator::Sqrt));
+    }
+
+    #[test]
+    fn test_variant_restricts_operators() {
+        // テスト: 特別ルールで使用できる演算子が絞られる
+        let rules = RuleSet::default().with_variant(RuleVariant::NoDivision);
+        assert!(!rules.allowed_operators().contains(Operator::Div));
+        assert_eq!(rules.allowed_operators().len(), 3);
+        assert_eq!(rules.description(), "No division: + - *");
+
+        let rules = RuleSet::default().with_variant(RuleVariant::OnlyAddMul);
+        assert_eq!(
+            rules.allowed_operators().iter().collect::<Vec<_>>(),
+            vec![Operator::Add, Operator::Mul]
+        );
+        assert_eq!(rules.card_count(), 4);
+
+        // 4つの演算子を1回ずつ使うには5枚のカードが必要
+        let rules = RuleSet::default().with_variant(RuleVariant::EachOperatorOnce);
+        assert_eq!(rules.card_count(), 5);
+        assert!(rules.can_use_operator(Operator::Add, &[Operator::Sub]));
+        assert!(!rules.can_use_operator(Operator::Sub, &[Operator::Sub]));
+    }
+
+    #[test]
+    fn test_check_rejects_violations() {
+        // テスト: ルールに違反する答えは理由とともに拒否される
+        let expr = Expr::parse("8 / 2 + 1 + 5").unwrap();
+        assert_eq!(RuleSet::default().check(&expr), Ok(()));
+        assert_eq!(
+            RuleSet::default()
+                .with_variant(RuleVariant::NoDivision)
+                .check(&expr),
+            Err(RuleViolation::ForbiddenOperator(Operator::Div))
+        );
+
+        let once = RuleSet::default().with_variant(RuleVariant::EachOperatorOnce);
+        assert_eq!(
+            once.check(&Expr::parse("(9 - 5) * 2 + 4 / 2").unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            once.check(&expr),
+            Err(RuleViolation::OperatorReused(Operator::Add))
+        );
+        assert_eq!(
+            once.check(&Expr::parse("2 * 5 + 1 - 1").unwrap()),
+            Err(RuleViolation::OperatorUnused(Operator::Div))
+        );
+    }
+
+    #[test]
+    fn test_special_stages() {
+        // テスト: 5ステージごとに特別ルールが順に出題される
+        assert_eq!(RuleVariant::for_stage(1), RuleVariant::Standard);
+        assert_eq!(RuleVariant::for_stage(4), RuleVariant::Standard);
+        assert_eq!(RuleVariant::for_stage(5), RuleVariant::NoDivision);
+        assert_eq!(RuleVariant::for_stage(10), RuleVariant::OnlyAddMul);
+        assert_eq!(RuleVariant::for_stage(15), RuleVariant::EachOperatorOnce);
+        assert_eq!(RuleVariant::for_stage(20), RuleVariant::NoDivision);
+    }
+
+    #[test]
+    fn test_intermediate_value_rules() {
+        // テスト: 途中の計算結果の制限
+        let rules = RuleSet::default()
+            .with_non_negative_intermediates(true)
+            .with_integer_intermediates(true);
+        assert_eq!(
+            rules.description(),
+            "Operators: + - * /, no negatives, integers only"
+        );
+        assert_eq!(
+            rules.check(&Expr::parse("4 * (3 - 1) + 2").unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            rules.check(&Expr::parse("(1 - 3) * (1 - 4)").unwrap()),
+            Err(RuleViolation::NegativeIntermediate(Rational::from_integer(
+                -2
+            )))
+        );
+        assert_eq!(
+            rules.check(&Expr::parse("8 / (1 - 1 / 5)").unwrap()),
+            Err(RuleViolation::FractionalIntermediate(
+                Rational::new(1, 5).unwrap()
+            ))
+        );
+        assert_eq!(
+            RuleSet::default().check(&Expr::parse("8 / (1 - 1 / 5)").unwrap()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_house_rules_apply_to_every_stage() {
+        // テスト: ハウスルールで有効にした演算子はステージが進んでも有効
+        let mut house_rules = HouseRules::default();
+        assert_eq!(house_rules.rules_for_stage(3), RuleSet::default());
+
+        house_rules.toggle_operator(Operator::Pow);
+        house_rules.toggle_operator(Operator::Sqrt);
+        for stage in [1, 2, 5, 10] {
+            let rules = house_rules.rules_for_stage(stage);
+            assert_eq!(rules.variant, RuleVariant::for_stage(stage));
+            assert!(rules.operators.contains(Operator::Pow));
+            assert!(rules.operators.contains(Operator::Sqrt));
+        }
+
+        // 演算子を1回ずつ使うステージは四則演算のみ
+        let rules = house_rules.rules_for_stage(15);
+        assert!(rules.each_operator_once());
+        assert_eq!(rules.card_count(), 5);
+
+        house_rules.toggle_operator(Operator::Pow);
+        assert!(
+            !house_rules
+                .rules_for_stage(1)
+                .operators
+                .contains(Operator::Pow)
+        );
+    }
+
+    #[test]
+    fn test_house_rules_restrict_intermediates() {
+        // テスト: ハウスルールの途中結果の制限は特別ルールのステージにも適用される
+        let house_rules = HouseRules {
+            non_negative_intermediates: true,
+            integer_intermediates: true,
+            ..default()
+        };
+        for stage in [1, 5, 15] {
+            let rules = house_rules.rules_for_stage(stage);
+            assert!(rules.non_negative_intermediates);
+            assert!(rules.integer_intermediates);
+        }
+        assert!(
+            !HouseRules::default()
+                .rules_for_stage(1)
+                .integer_intermediates
+        );
+    }
+
+    #[test]
+    fn test_digit_count_grows_with_stage() {
+        // テスト: 序盤は3枚、終盤は5枚や6枚のカードで出題し、枚数はゲームで扱える範囲に収まる
+        let house_rules = HouseRules::default();
+        assert_eq!(house_rules.rules_for_stage(1).card_count(), 3);
+        assert_eq!(house_rules.rules_for_stage(3).card_count(), 4);
+        assert_eq!(house_rules.rules_for_stage(21).card_count(), 5);
+        assert_eq!(house_rules.rules_for_stage(31).card_count(), 6);
+
+        // 特別ルールのステージや拡張演算子を使う場合は4枚まで
+        assert_eq!(house_rules.rules_for_stage(25).card_count(), 4);
+        let mut extended = house_rules.clone();
+        extended.toggle_operator(Operator::Pow);
+        assert_eq!(extended.rules_for_stage(21).card_count(), 4);
+        assert_eq!(extended.rules_for_stage(31).card_count(), 4);
+        for stage in 0..=100 {
+            let rules = house_rules.rules_for_stage(stage);
+            assert!(RuleSet::DIGIT_COUNTS.contains(&rules.card_count()));
+        }
+    }
+}
@@ -0,0 +1,655 @@
+//! 盤面の解の空間
+//!
+//! 数字の部分集合ごとに作れる値を動的計画法で求め、式そのものは解に必要な分だけ組み立てる。
+//! すべての式を列挙しないため、5枚・6枚の盤面でも難易度や解の一覧を現実的な時間で求められる。
+
+use crate::game::{
+    CanonicalExpr, Difficulty, Expr, GameNumbers, Operator, OperatorSet, Rational, RuleSet,
+    apply_operator,
+};
+use std::collections::{HashMap, HashSet};
+
+/// 部分式の状態（値、使った演算子、直前に単項演算を適用したか、数字を並べただけか）
+///
+/// 状態が同じ部分式どうしは、その先の組み合わせ方がまったく同じになる。
+type StateKey = (Rational, OperatorSet, bool, bool);
+
+/// 同じ状態になる部分式の性質（それぞれ最も有利なもの）
+#[derive(Debug, Clone, Copy)]
+struct Summary {
+    /// 式の木の深さの最小値
+    depth: usize,
+    /// 割り算を使わずに作れるか
+    without_division: bool,
+    /// 途中で分数を経由せずに作れるか
+    without_fraction: bool,
+    /// 途中で負の数を経由せずに作れるか
+    without_negative: bool,
+}
+
+impl Summary {
+    /// 数字そのもの
+    const LITERAL: Self = Self {
+        depth: 0,
+        without_division: true,
+        without_fraction: true,
+        without_negative: true,
+    };
+
+    /// 演算を1回適用した結果
+    fn then(self, op: Operator, value: Rational) -> Self {
+        Self {
+            depth: self.depth + 1,
+            without_division: self.without_division && op != Operator::Div,
+            without_fraction: self.without_fraction && value.is_integer(),
+            without_negative: self.without_negative && !value.is_negative(),
+        }
+    }
+
+    /// 2つの部分式を二項演算でまとめた結果
+    fn join(self, op: Operator, rhs: Self, value: Rational) -> Self {
+        Self {
+            depth: self.depth.max(rhs.depth),
+            without_division: self.without_division && rhs.without_division,
+            without_fraction: self.without_fraction && rhs.without_fraction,
+            without_negative: self.without_negative && rhs.without_negative,
+        }
+        .then(op, value)
+    }
+
+    /// 同じ状態になる別の部分式の性質を取り込む
+    fn merge(&mut self, other: Self) {
+        self.depth = self.depth.min(other.depth);
+        self.without_division |= other.without_division;
+        self.without_fraction |= other.without_fraction;
+        self.without_negative |= other.without_negative;
+    }
+}
+
+/// 2つの状態を二項演算でまとめる組み合わせ
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    /// 左の部分式に使う数字の部分集合
+    left_mask: u32,
+    /// 左の状態
+    left: usize,
+    /// 右の状態
+    right: usize,
+    op: Operator,
+}
+
+/// 数字の部分集合から作れる状態の一覧
+#[derive(Debug, Default)]
+struct Layer {
+    states: Vec<(StateKey, Summary)>,
+    index: HashMap<StateKey, usize>,
+    /// 値ごとの状態
+    by_value: HashMap<Rational, Vec<usize>>,
+    /// 単項演算による状態のつながり（適用後の状態、演算子、適用前の状態）
+    unary_edges: Vec<(usize, Operator, usize)>,
+}
+
+impl Layer {
+    /// 状態を追加する（既にある場合は性質をまとめる）
+    fn insert(&mut self, key: StateKey, summary: Summary) -> usize {
+        if let Some(&state) = self.index.get(&key) {
+            self.states[state].1.merge(summary);
+            return state;
+        }
+        let state = self.states.len();
+        self.states.push((key, summary));
+        self.index.insert(key, state);
+        self.by_value.entry(key.0).or_default().push(state);
+        state
+    }
+
+    /// 単項演算を適用した状態を追加する（続けて適用はしない）
+    fn apply_unary(&mut self, rules: &RuleSet, keep: impl Fn(Rational) -> bool) {
+        let once = rules.each_operator_once();
+        for source in 0..self.states.len() {
+            let ((value, used, unary_applied, _), summary) = self.states[source];
+            if unary_applied {
+                continue;
+            }
+            for op in rules.allowed_operators().unary() {
+                if once && used.contains(op) {
+                    continue;
+                }
+                if let Ok(result) = op.apply_unary(value)
+                    && result != value
+                    && rules.check_intermediate(result).is_ok()
+                    && keep(result)
+                {
+                    let used = if once { used.with(op) } else { used };
+                    let state = self.insert((result, used, true, false), summary.then(op, result));
+                    self.unary_edges.push((state, op, source));
+                }
+            }
+        }
+    }
+}
+
+/// 解の代表の式
+struct Representative {
+    state: usize,
+    expr: Expr,
+}
+
+/// 部分集合ごとの代表の式を、正規形が重複しないように上限まで集める
+struct Representatives {
+    list: Vec<Representative>,
+    seen: HashSet<CanonicalExpr>,
+    counts: Vec<usize>,
+    limit: usize,
+}
+
+impl Representatives {
+    fn new(states: usize, limit: usize) -> Self {
+        Self {
+            list: Vec::new(),
+            seen: HashSet::new(),
+            counts: vec![0; states],
+            limit,
+        }
+    }
+
+    fn push(&mut self, state: usize, expr: impl FnOnce() -> Expr) {
+        if self.counts[state] >= self.limit {
+            return;
+        }
+        let expr = expr();
+        if self.seen.insert(expr.canonical()) {
+            self.counts[state] += 1;
+            self.list.push(Representative { state, expr });
+        }
+    }
+}
+
+/// 盤面の解の空間
+///
+/// 作成時には部分集合ごとの状態だけを求め、式は`distinct_solutions`で必要な分だけ組み立てる。
+pub struct SolutionSpace {
+    digits: Vec<u8>,
+    rules: RuleSet,
+    /// 数字の部分集合（ビットマスク）ごとの状態
+    layers: Vec<Layer>,
+}
+
+impl SolutionSpace {
+    /// 部分集合の小さい順に、作れる状態をすべて求める
+    ///
+    /// すべての数字を使った状態は、目標の数（単項演算を適用した後を含む）になるものだけ残す。
+    pub fn new(numbers: &GameNumbers, rules: &RuleSet) -> Self {
+        let digits = numbers.digits.clone();
+        let full_mask = (1u32 << digits.len()) - 1;
+        let target = rules.target_value();
+        let roots = root_values(rules);
+
+        let mut layers = vec![Layer::default()];
+        for mask in 1..=full_mask {
+            let mut layer = Layer::default();
+            if mask.count_ones() == 1 {
+                let digit = digits[mask.trailing_zeros() as usize];
+                let key = (Rational::from(digit), OperatorSet::EMPTY, false, true);
+                layer.insert(key, Summary::LITERAL);
+            } else {
+                let targets = (mask == full_mask).then_some(roots.as_slice());
+                combine(&layers, rules, mask, targets, |_, key, summary| {
+                    layer.insert(key, summary);
+                });
+            }
+            layer.apply_unary(rules, |value| mask != full_mask || value == target);
+            layers.push(layer);
+        }
+
+        Self {
+            digits,
+            rules: rules.clone(),
+            layers,
+        }
+    }
+
+    fn full_mask(&self) -> u32 {
+        (1u32 << self.digits.len()) - 1
+    }
+
+    /// すべての数字を使って目標の数になる状態
+    fn solution_states(&self) -> impl Iterator<Item = usize> + '_ {
+        let target = self.rules.target_value();
+        let allowed = self.rules.allowed_operators();
+        let once = self.rules.each_operator_once();
+        self.layers[self.full_mask() as usize]
+            .states
+            .iter()
+            .enumerate()
+            .filter(move |(_, ((value, used, _, _), _))| {
+                *value == target && (!once || *used == allowed)
+            })
+            .map(|(state, _)| state)
+    }
+
+    /// 目標の数を作れるか
+    pub fn is_solvable(&self) -> bool {
+        self.solution_states().next().is_some()
+    }
+
+    /// 盤面の難易度（解けない盤面の場合は`None`）
+    ///
+    /// 解の数は`limit`個まで数え、それ以外の性質はすべての解から求める。
+    pub fn difficulty(&self, limit: usize) -> Option<Difficulty> {
+        let layer = &self.layers[self.full_mask() as usize];
+        let summaries: Vec<Summary> = self
+            .solution_states()
+            .map(|state| layer.states[state].1)
+            .collect();
+        if summaries.is_empty() {
+            return None;
+        }
+
+        Some(Difficulty {
+            distinct_solutions: self.distinct_solutions(limit).len(),
+            requires_division: !summaries.iter().any(|s| s.without_division),
+            requires_fraction: !summaries.iter().any(|s| s.without_fraction),
+            requires_negative: !summaries.iter().any(|s| s.without_negative),
+            min_depth: summaries.iter().map(|s| s.depth).min().unwrap_or(0),
+        })
+    }
+
+    /// 本質的に異なる解を`limit`個まで列挙（正規形ごとに代表の式を1つ返す）
+    ///
+    /// 代表の式は、すべての式を部分集合の分け方・左右の式・演算子の順に並べたときに
+    /// 最初に現れるものになる。
+    pub fn distinct_solutions(&self, limit: usize) -> Vec<Expr> {
+        let full_mask = self.full_mask() as usize;
+        let (relevant, edges) = self.relevant_states();
+
+        // 部分集合の小さい順に、解に必要な状態の代表の式を組み立てる
+        let mut representatives: Vec<Vec<Representative>> = Vec::with_capacity(self.layers.len());
+        let mut by_state: Vec<HashMap<usize, Vec<usize>>> = Vec::with_capacity(self.layers.len());
+        representatives.push(Vec::new());
+        by_state.push(HashMap::new());
+        for mask in 1..=full_mask {
+            let layer = &self.layers[mask];
+            let mut found = Representatives::new(layer.states.len(), limit);
+            if relevant[mask].contains(&true) {
+                if mask.count_ones() == 1 {
+                    let index = mask.trailing_zeros() as usize;
+                    found.push(0, || Expr::literal(index, self.digits[index]));
+                } else {
+                    self.combine_representatives(
+                        &edges[mask],
+                        mask as u32,
+                        &representatives,
+                        &by_state,
+                        &mut found,
+                    );
+                }
+
+                // 単項演算の代表は、二項演算の代表の後に並べる
+                let unary: HashMap<(usize, Operator), usize> = layer
+                    .unary_edges
+                    .iter()
+                    .filter(|&&(state, _, _)| relevant[mask][state])
+                    .map(|&(state, op, source)| ((source, op), state))
+                    .collect();
+                for i in 0..found.list.len() {
+                    for op in self.rules.allowed_operators().unary() {
+                        let source = found.list[i].state;
+                        if let Some(&state) = unary.get(&(source, op)) {
+                            let operand = found.list[i].expr.clone();
+                            found.push(state, || Expr::unary(op, operand));
+                        }
+                    }
+                }
+            }
+
+            let mut states: HashMap<usize, Vec<usize>> = HashMap::new();
+            for (position, representative) in found.list.iter().enumerate() {
+                states
+                    .entry(representative.state)
+                    .or_default()
+                    .push(position);
+            }
+            representatives.push(found.list);
+            by_state.push(states);
+        }
+
+        let solutions: HashSet<usize> = self.solution_states().collect();
+        representatives
+            .swap_remove(full_mask)
+            .into_iter()
+            .filter(|representative| solutions.contains(&representative.state))
+            .map(|representative| representative.expr)
+            .take(limit)
+            .collect()
+    }
+
+    /// 部分集合を左右に分けた代表の式を組み合わせる
+    ///
+    /// 分け方ごとに、左の代表の順・右の代表の順・演算子の順に試す。
+    fn combine_representatives(
+        &self,
+        edges: &[(Edge, usize)],
+        mask: u32,
+        representatives: &[Vec<Representative>],
+        by_state: &[HashMap<usize, Vec<usize>>],
+        found: &mut Representatives,
+    ) {
+        // 組み合わせは分け方の順に並んでいる
+        for split in edges.chunk_by(|(a, _), (b, _)| a.left_mask == b.left_mask) {
+            let left_mask = split[0].0.left_mask as usize;
+            let right_mask = (mask & !split[0].0.left_mask) as usize;
+            let mut links: HashMap<usize, Vec<(usize, Operator, usize)>> = HashMap::new();
+            for &(edge, parent) in split {
+                links
+                    .entry(edge.left)
+                    .or_default()
+                    .push((edge.right, edge.op, parent));
+            }
+
+            for left in &representatives[left_mask] {
+                let Some(links) = links.get(&left.state) else {
+                    continue;
+                };
+                let mut candidates: Vec<(usize, Operator, usize)> = links
+                    .iter()
+                    .flat_map(|&(right, op, parent)| {
+                        by_state[right_mask]
+                            .get(&right)
+                            .into_iter()
+                            .flatten()
+                            .map(move |&position| (position, op, parent))
+                    })
+                    .collect();
+                candidates.sort_by_key(|&(position, op, _)| (position, op));
+                for (position, op, parent) in candidates {
+                    let right = &representatives[right_mask][position];
+                    found.push(parent, || {
+                        Expr::binary(op, left.expr.clone(), right.expr.clone())
+                    });
+                }
+            }
+        }
+    }
+
+    /// 解に使われる状態と、それらを二項演算で作る組み合わせを求める
+    ///
+    /// すべての数字を使った解の状態から、部分集合の大きい順にたどる。
+    #[allow(clippy::type_complexity)]
+    fn relevant_states(&self) -> (Vec<Vec<bool>>, Vec<Vec<(Edge, usize)>>) {
+        let full_mask = self.full_mask();
+        let mut relevant: Vec<Vec<bool>> = self
+            .layers
+            .iter()
+            .map(|layer| vec![false; layer.states.len()])
+            .collect();
+        let mut edges: Vec<Vec<(Edge, usize)>> = self.layers.iter().map(|_| Vec::new()).collect();
+        for state in self.solution_states() {
+            relevant[full_mask as usize][state] = true;
+        }
+
+        for mask in (1..=full_mask).rev() {
+            let layer = &self.layers[mask as usize];
+            for &(state, _, source) in &layer.unary_edges {
+                if relevant[mask as usize][state] {
+                    relevant[mask as usize][source] = true;
+                }
+            }
+            if mask.count_ones() == 1 || !relevant[mask as usize].contains(&true) {
+                continue;
+            }
+
+            let needed = &relevant[mask as usize];
+            let roots = root_values(&self.rules);
+            let targets = (mask == full_mask).then_some(roots.as_slice());
+            let mut found = Vec::new();
+            combine(&self.layers, &self.rules, mask, targets, |edge, key, _| {
+                if let Some(&parent) = layer.index.get(&key)
+                    && needed[parent]
+                {
+                    found.push((edge, parent));
+                }
+            });
+            for (edge, _) in &found {
+                relevant[edge.left_mask as usize][edge.left] = true;
+                relevant[(mask & !edge.left_mask) as usize][edge.right] = true;
+            }
+            edges[mask as usize] = found;
+        }
+
+        (relevant, edges)
+    }
+}
+
+/// すべての数字を使った式の最後の二項演算の結果として使える値
+///
+/// 目標の数そのものと、単項演算を適用すると目標の数になる値。
+fn root_values(rules: &RuleSet) -> Vec<Rational> {
+    let target = rules.target_value();
+    let mut values = vec![target];
+    for op in rules.allowed_operators().unary() {
+        let candidates: Vec<Rational> = match op {
+            Operator::Sqrt => target.checked_mul(target).into_iter().collect(),
+            // 階乗は計算できる範囲の整数を順に試す
+            _ => (0..)
+                .map(Rational::from_integer)
+                .take_while(|&value| op.apply_unary(value).is_ok())
+                .collect(),
+        };
+        values.extend(
+            candidates
+                .into_iter()
+                .filter(|&value| value != target && op.apply_unary(value) == Ok(target)),
+        );
+    }
+    values
+}
+
+/// `lhs op rhs`が`target`になる`rhs`を逆算できるか
+fn is_invertible(op: Operator, lhs: Rational, target: Rational) -> bool {
+    match op {
+        Operator::Add | Operator::Sub => true,
+        Operator::Mul => !lhs.is_zero(),
+        Operator::Div => !target.is_zero(),
+        _ => false,
+    }
+}
+
+/// `lhs op rhs`が`target`になる`rhs`（オーバーフローする場合は`None`）
+fn required_operand(op: Operator, lhs: Rational, target: Rational) -> Option<Rational> {
+    match op {
+        Operator::Add => target.checked_sub(lhs),
+        Operator::Sub => lhs.checked_sub(target),
+        Operator::Mul => target.checked_div(lhs),
+        Operator::Div => lhs.checked_div(target),
+        _ => None,
+    }
+}
+
+/// 部分集合`mask`を左右に分け、左右の状態を二項演算でまとめる組み合わせをすべて試す
+///
+/// `targets`を指定した場合は、結果がそのいずれかになる組み合わせだけを試す
+/// （四則演算は右の値を逆算して探す）。
+/// 演算子を1回ずつ使うルールでは、同じ演算子を2回使う組み合わせは作らない。
+fn combine(
+    layers: &[Layer],
+    rules: &RuleSet,
+    mask: u32,
+    targets: Option<&[Rational]>,
+    mut visit: impl FnMut(Edge, StateKey, Summary),
+) {
+    let allowed = rules.allowed_operators();
+    let once = rules.each_operator_once();
+    let mut candidates = Vec::new();
+    let mut left_mask = (mask - 1) & mask;
+    while left_mask > 0 {
+        let right_mask = mask & !left_mask;
+        let lefts = &layers[left_mask as usize].states;
+        let rights = &layers[right_mask as usize];
+        for (left, &((lhs, left_used, _, left_digits), left_summary)) in lefts.iter().enumerate() {
+            for op in allowed.binary() {
+                // 可換な演算は片方の分け方だけ試せば十分
+                if op.is_commutative() && left_mask < right_mask {
+                    continue;
+                }
+                if once && left_used.contains(op) {
+                    continue;
+                }
+
+                candidates.clear();
+                match targets
+                    .filter(|targets| targets.iter().all(|&target| is_invertible(op, lhs, target)))
+                {
+                    Some(targets) => {
+                        for &target in targets {
+                            let operand = required_operand(op, lhs, target);
+                            candidates.extend(
+                                operand
+                                    .and_then(|operand| rights.by_value.get(&operand))
+                                    .into_iter()
+                                    .flatten(),
+                            );
+                        }
+                    }
+                    None => candidates.extend(0..rights.states.len()),
+                }
+
+                for &right in &candidates {
+                    let ((rhs, right_used, _, right_digits), right_summary) = rights.states[right];
+                    if once && (!left_used.is_disjoint(right_used) || right_used.contains(op)) {
+                        continue;
+                    }
+                    // 連結できるのは数字を並べたものだけ
+                    let digits = op == Operator::Concat;
+                    if digits && !(left_digits && right_digits) {
+                        continue;
+                    }
+                    if let Ok(value) = apply_operator(op, lhs, rhs)
+                        && rules.check_intermediate(value).is_ok()
+                        && targets.is_none_or(|targets| targets.contains(&value))
+                    {
+                        let used = if once {
+                            left_used.union(right_used).with(op)
+                        } else {
+                            left_used
+                        };
+                        let edge = Edge {
+                            left_mask,
+                            left,
+                            right,
+                            op,
+                        };
+                        visit(
+                            edge,
+                            (value, used, false, digits),
+                            left_summary.join(op, right_summary, value),
+                        );
+                    }
+                }
+            }
+        }
+        left_mask = (left_mask - 1) & mask;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Calculator, RuleVariant};
+
+    /// すべての式を列挙してから正規形で重複を除いた解
+    fn enumerated_distinct_solutions(numbers: &GameNumbers, rules: &RuleSet) -> Vec<Expr> {
+        let mut seen = HashSet::new();
+        Calculator::all_solutions(numbers, rules)
+            .into_iter()
+            .filter(|expr| seen.insert(expr.canonical()))
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_full_enumeration() {
+        // テスト: すべての式を列挙した場合と同じ代表の式が同じ順に得られる
+        let boards: [&[u8]; 6] = [
+            &[1, 2, 3, 4],
+            &[1, 1, 5, 8],
+            &[3, 4, 7, 8],
+            &[0, 0, 0, 0],
+            &[2, 3, 4],
+            &[4, 4, 9, 1],
+        ];
+        let rule_sets = [
+            RuleSet::default(),
+            RuleSet::with_target(24),
+            RuleSet::default().with_operator(Operator::Sqrt),
+            RuleSet::default().with_non_negative_intermediates(true),
+        ];
+        for digits in boards {
+            for rules in &rule_sets {
+                let numbers = GameNumbers::from_digits(digits.to_vec());
+                let space = SolutionSpace::new(&numbers, rules);
+                let expected = enumerated_distinct_solutions(&numbers, rules);
+                assert_eq!(
+                    space.distinct_solutions(usize::MAX),
+                    expected,
+                    "{:?}",
+                    digits
+                );
+                assert_eq!(space.is_solvable(), !expected.is_empty(), "{:?}", digits);
+                // 深さだけは代表の式に限らずすべての解から求めるため、浅くなることがある
+                match (
+                    space.difficulty(usize::MAX),
+                    Difficulty::from_solutions(&expected),
+                ) {
+                    (Some(difficulty), Some(enumerated)) => {
+                        assert!(difficulty.min_depth <= enumerated.min_depth);
+                        assert_eq!(
+                            difficulty,
+                            Difficulty {
+                                min_depth: difficulty.min_depth,
+                                ..enumerated
+                            },
+                            "{:?}",
+                            digits
+                        );
+                    }
+                    (difficulty, enumerated) => assert_eq!(difficulty, enumerated),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_solution_limit() {
+        // テスト: 上限を指定すると先頭から上限個だけ返す
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let space = SolutionSpace::new(&numbers, &RuleSet::default());
+        let all = space.distinct_solutions(usize::MAX);
+        assert!(all.len() > 5);
+        assert_eq!(space.distinct_solutions(5), all[..5]);
+        assert_eq!(space.difficulty(5).unwrap().distinct_solutions, 5);
+    }
+
+    #[test]
+    fn test_each_operator_once_with_five_cards() {
+        // テスト: 四則演算を1回ずつ使う解はすべてルールを守っている
+        let rules = RuleSet::default().with_variant(RuleVariant::EachOperatorOnce);
+        let numbers = GameNumbers::from_digits([9, 5, 2, 4, 2]);
+        let solutions = SolutionSpace::new(&numbers, &rules).distinct_solutions(usize::MAX);
+        assert_eq!(solutions, enumerated_distinct_solutions(&numbers, &rules));
+        for solution in &solutions {
+            assert_eq!(rules.check(solution), Ok(()), "{}", solution);
+        }
+    }
+
+    #[test]
+    fn test_six_cards_can_be_rated() {
+        // テスト: すべての式を列挙するとメモリが足りなくなる6枚の盤面も評価できる
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4, 5, 6]);
+        let difficulty = Calculator::difficulty(&numbers, &RuleSet::default()).unwrap();
+        assert_eq!(
+            difficulty.distinct_solutions,
+            crate::game::DIFFICULTY_SOLUTION_LIMIT
+        );
+        assert_eq!(difficulty.stars(), 1);
+    }
+}
@@ -1,15 +1,28 @@
 #[cfg(test)]
 mod tests {
-    use crate::game::{Calculator, GameNumbers};
+    use crate::game::{Calculator, GameNumbers, PuzzleRng, RuleSet, SolvabilityTable};
 
     #[test]
     fn test_all_generated_numbers_are_solvable() {
-        // 複数回生成して、すべてが解けることを確認
+        // 生成元の表の解ける組み合わせが、すべてソルバーでも解けることを確認
+        let table = SolvabilityTable::for_rules(&RuleSet::default()).unwrap();
+        for entry in table.solvable_entries() {
+            let numbers = GameNumbers::from_digits(entry.digits.clone());
+            assert!(
+                Calculator::can_make_ten(&numbers),
+                "表の組み合わせ {:?} で10を作ることができません",
+                entry.digits
+            );
+        }
+
+        // 生成された盤面が表の解ける組み合わせであることを確認
+        let mut rng = PuzzleRng::from_seed(100);
         for i in 0..100 {
-            let numbers = GameNumbers::new();
+            let numbers = GameNumbers::generate(&RuleSet::default(), &mut rng).unwrap();
+            let entry = table.get(&numbers.digits).unwrap();
 
             assert!(
-                Calculator::can_make_ten(&numbers),
+                entry.solvable,
                 "生成された数字 {:?} (試行 {}) で10を作ることができません",
                 numbers.digits,
                 i + 1
@@ -36,11 +49,11 @@ mod tests {
     fn test_generated_numbers_have_valid_digits() {
         // 生成された数字が1-9の範囲内であることを確認（Make10ゲーム用）
         for _ in 0..50 {
-            let numbers = GameNumbers::new();
+            let numbers = GameNumbers::random();
 
             for &digit in &numbers.digits {
                 assert!(
-                    digit >= 1 && digit <= 9,
+                    (1..=9).contains(&digit),
                     "生成された数字 {} が有効範囲（1-9）外です。数字セット: {:?}",
                     digit,
                     numbers.digits
@@ -1,17 +1,50 @@
 //! ゲーム状態管理
 
+use crate::game::{HINT_COST, MAX_HINT_LEVEL};
 use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+/// ギブアップしたときに減るスコア
+pub const GIVE_UP_PENALTY: u32 = 50;
+
+/// ゲーム開始時のライフの数
+pub const STARTING_LIVES: u32 = 3;
 
 /// ゲームの状態を表すenum
-#[derive(Debug, Clone, PartialEq, Default, Resource)]
+///
+/// 各状態で表示するUIは`StateScoped`で管理され、状態を抜けると自動で削除される。
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[states(scoped_entities)]
 pub enum GameState {
-    /// ゲーム中
+    /// メインメニュー
     #[default]
+    MainMenu,
+    /// ゲーム中
     Playing,
+    /// 一時停止中
+    Paused,
     /// ステージクリア
     StageClear,
+    /// ギブアップして解答を表示中
+    GaveUp,
     /// ゲームオーバー
     GameOver,
+    /// タイムアタックの時間切れで結果を表示中
+    TimeUp,
+}
+
+/// ゲーム画面を表示している状態（メインメニュー以外）
+///
+/// 一時停止やポップアップの表示中もゲーム画面は残しておく。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = GameState;
+
+    fn compute(state: GameState) -> Option<Self> {
+        (state != GameState::MainMenu).then_some(InGame)
+    }
 }
 
 /// ゲーム進行状態を管理するリソース
@@ -20,6 +53,16 @@ pub struct GameProgress {
     pub current_stage: u32,
     pub score: u32,
     pub stages_cleared: u32,
+    /// ステージごとに使ったヒントの数
+    pub hints_by_stage: BTreeMap<u32, u32>,
+    /// ギブアップした回数
+    pub stages_given_up: u32,
+    /// 残りライフ（間違えるかギブアップするたびに1つ減る）
+    pub lives: u32,
+    /// ライフを失わずに続けてクリアしたステージ数
+    pub streak: u32,
+    /// これまでの最長の連続クリア数
+    pub best_streak: u32,
 }
 
 impl Default for GameProgress {
@@ -28,18 +71,220 @@ impl Default for GameProgress {
             current_stage: 1, // ステージ1から開始
             score: 0,
             stages_cleared: 0,
+            hints_by_stage: BTreeMap::new(),
+            stages_given_up: 0,
+            lives: STARTING_LIVES,
+            streak: 0,
+            best_streak: 0,
         }
     }
 }
 
+impl GameProgress {
+    /// 現在のステージで使ったヒントの数
+    pub fn hints_used(&self) -> u32 {
+        self.hints_by_stage
+            .get(&self.current_stage)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// ヒントを1つ使い、スコアを減らす
+    ///
+    /// 新しいヒントの段階を返す。すべての段階を使い切っている場合は`None`。
+    pub fn use_hint(&mut self) -> Option<u32> {
+        let used = self.hints_used();
+        if used >= MAX_HINT_LEVEL {
+            return None;
+        }
+        self.hints_by_stage.insert(self.current_stage, used + 1);
+        self.score = self.score.saturating_sub(HINT_COST);
+        Some(used + 1)
+    }
+
+    /// 現在のステージの盤面が差し替えられたときに、前の盤面で使ったヒントを消す
+    ///
+    /// ヒントは盤面ごとのものなので、新しい盤面では最初の段階から使い直す。
+    /// 差し替えで難しい盤面を飛ばせないよう、前の盤面はギブアップとして扱う。
+    /// ライフがなくなった場合は`true`を返す。
+    pub fn replace_board(&mut self) -> bool {
+        self.hints_by_stage.remove(&self.current_stage);
+        self.give_up()
+    }
+
+    /// 現在のステージをクリアし、スコアと連続クリア数を増やす
+    pub fn clear_stage(&mut self) {
+        self.stages_cleared += 1;
+        self.score += 100; // ステージクリアごとに100ポイント
+        self.streak += 1;
+        self.best_streak = self.best_streak.max(self.streak);
+    }
+
+    /// ライフを1つ失い、連続クリアを途切れさせる
+    ///
+    /// ライフがなくなった場合は`true`を返す。
+    pub fn lose_life(&mut self) -> bool {
+        self.lives = self.lives.saturating_sub(1);
+        self.streak = 0;
+        self.is_game_over()
+    }
+
+    /// ライフがなくなったか
+    pub fn is_game_over(&self) -> bool {
+        self.lives == 0
+    }
+
+    /// 現在のステージをギブアップし、スコアとライフを減らす
+    ///
+    /// ライフがなくなった場合は`true`を返す。
+    pub fn give_up(&mut self) -> bool {
+        self.stages_given_up += 1;
+        self.score = self.score.saturating_sub(GIVE_UP_PENALTY);
+        self.lose_life()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use bevy::state::app::StatesPlugin;
+
     #[test]
-    fn test_default_state_is_playing() {
-        // テスト: デフォルト状態がPlayingであることを確認
+    fn test_default_state_is_main_menu() {
+        // テスト: デフォルト状態がMainMenuであることを確認
         let state = GameState::default();
-        assert_eq!(state, GameState::Playing);
+        assert_eq!(state, GameState::MainMenu);
+    }
+
+    #[test]
+    fn test_in_game_state_and_scoped_entities() {
+        // テスト: メインメニュー以外ではInGameになり、状態を抜けるとスコープ付きのエンティティが削除される
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_computed_state::<InGame>()
+            .enable_state_scoped_entities::<InGame>();
+        app.update();
+        assert!(app.world().get_resource::<State<InGame>>().is_none());
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+        assert_eq!(**app.world().resource::<State<InGame>>(), InGame);
+
+        let screen = app.world_mut().spawn(StateScoped(InGame)).id();
+        let popup = app
+            .world_mut()
+            .spawn(StateScoped(GameState::StageClear))
+            .id();
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Paused);
+        app.update();
+        assert!(app.world().get_entity(screen).is_ok());
+        // StageClearに入っていない状態から抜けても削除されない
+        assert!(app.world().get_entity(popup).is_ok());
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::StageClear);
+        app.update();
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+        assert!(app.world().get_entity(popup).is_err());
+        assert!(app.world().get_entity(screen).is_ok());
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::MainMenu);
+        app.update();
+        assert!(app.world().get_entity(screen).is_err());
+    }
+
+    #[test]
+    fn test_hints_cost_score_and_are_recorded_per_stage() {
+        // テスト: ヒントはスコアを消費し、ステージごとに数が記録される
+        let mut progress = GameProgress {
+            score: 50,
+            ..default()
+        };
+        assert_eq!(progress.use_hint(), Some(1));
+        assert_eq!(progress.use_hint(), Some(2));
+        assert_eq!(progress.score, 50 - 2 * HINT_COST);
+        assert_eq!(progress.use_hint(), Some(3));
+        assert_eq!(progress.score, 0);
+        assert_eq!(progress.use_hint(), None);
+        assert_eq!(progress.hints_used(), MAX_HINT_LEVEL);
+
+        progress.current_stage += 1;
+        assert_eq!(progress.hints_used(), 0);
+        assert_eq!(progress.use_hint(), Some(1));
+        assert_eq!(progress.hints_by_stage.get(&1), Some(&MAX_HINT_LEVEL));
+        assert_eq!(progress.hints_by_stage.get(&2), Some(&1));
+
+        // 盤面を差し替えると、そのステージのヒントは最初から使い直す
+        progress.replace_board();
+        assert_eq!(progress.hints_used(), 0);
+        assert_eq!(progress.hints_by_stage.get(&1), Some(&MAX_HINT_LEVEL));
+    }
+
+    #[test]
+    fn test_replacing_the_board_counts_as_giving_up() {
+        // テスト: 盤面を差し替えるとギブアップと同じペナルティを受ける
+        let mut progress = GameProgress {
+            score: 100,
+            streak: 2,
+            ..default()
+        };
+        assert!(!progress.replace_board());
+        assert_eq!(progress.score, 100 - GIVE_UP_PENALTY);
+        assert_eq!(progress.stages_given_up, 1);
+        assert_eq!(progress.lives, STARTING_LIVES - 1);
+        assert_eq!(progress.streak, 0);
+
+        // 最後のライフで差し替えるとゲームオーバー
+        progress.lives = 1;
+        assert!(progress.replace_board());
+    }
+
+    #[test]
+    fn test_give_up_applies_penalty() {
+        // テスト: ギブアップするとスコアが減り、回数が記録される
+        let mut progress = GameProgress {
+            score: 120,
+            ..default()
+        };
+        assert!(!progress.give_up());
+        assert_eq!(progress.score, 120 - GIVE_UP_PENALTY);
+        assert_eq!(progress.stages_given_up, 1);
+        assert_eq!(progress.stages_cleared, 0);
+        assert_eq!(progress.lives, STARTING_LIVES - 1);
+
+        // スコアは0未満にならず、ライフがなくなるとゲームオーバー
+        assert!(!progress.give_up());
+        assert!(progress.give_up());
+        assert_eq!(progress.score, 0);
+        assert!(progress.is_game_over());
+    }
+
+    #[test]
+    fn test_streak_is_broken_by_losing_a_life() {
+        // テスト: 連続クリア数はライフを失うと途切れ、最長記録は残る
+        let mut progress = GameProgress::default();
+        progress.clear_stage();
+        progress.clear_stage();
+        progress.clear_stage();
+        assert_eq!((progress.streak, progress.best_streak), (3, 3));
+        assert_eq!(progress.score, 300);
+
+        assert!(!progress.lose_life());
+        progress.clear_stage();
+        assert_eq!((progress.streak, progress.best_streak), (1, 3));
+        assert_eq!(progress.stages_cleared, 4);
+        assert_eq!(progress.lives, STARTING_LIVES - 1);
     }
 }
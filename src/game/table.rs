@@ -0,0 +1,208 @@
+//! 数字の組み合わせごとの解けるかどうかの表
+//!
+//! 数字の並び順は解けるかどうかに影響しないため、ソート済みの組み合わせ（多重集合）ごとに
+//! 1度だけソルバーを実行して結果を表にする。4枚（0-9）なら715通りしかない。
+
+use crate::game::{Calculator, Difficulty, GameNumbers, OperatorSet, RuleSet};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// 表を作る最大の枚数（5枚以上は組み合わせが多く作成に時間がかかる）
+pub const TABLE_MAX_DIGITS: usize = 4;
+
+/// 表の1行（ソート済みの数字の組み合わせ）
+#[derive(Debug)]
+pub struct TableEntry {
+    /// ソート済みの数字
+    pub digits: Vec<u8>,
+    /// 目標の数を作れるか
+    pub solvable: bool,
+    /// 難易度（解の列挙は重いため、初めて参照したときに計算する）
+    difficulty: OnceLock<Option<Difficulty>>,
+}
+
+/// ルールごとの解けるかどうかの表
+#[derive(Debug)]
+pub struct SolvabilityTable {
+    rules: RuleSet,
+    entries: Vec<TableEntry>,
+    index: HashMap<Vec<u8>, usize>,
+}
+
+impl SolvabilityTable {
+    /// 0-9の数字のすべての組み合わせについて表を作成
+    pub fn build(rules: &RuleSet) -> Self {
+        let mut entries = Vec::new();
+        let mut digits = Vec::with_capacity(rules.card_count());
+        collect_multisets(rules.card_count(), 0, &mut digits, &mut |digits| {
+            let numbers = GameNumbers::from_digits(digits.to_vec());
+            entries.push(TableEntry {
+                digits: digits.to_vec(),
+                solvable: Calculator::can_make_target(&numbers, rules),
+                difficulty: OnceLock::new(),
+            });
+        });
+
+        let index = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.digits.clone(), i))
+            .collect();
+
+        Self {
+            rules: rules.clone(),
+            entries,
+            index,
+        }
+    }
+
+    /// ルールに対応する表を取得（初回のみ作成し、以降は使い回す）
+    ///
+    /// 枚数が`TABLE_MAX_DIGITS`を超える場合や、四則演算以外の演算子を使える場合
+    /// （解き方が多く、表の作成と難易度の計算に時間がかかる）は`None`。
+    pub fn for_rules(rules: &RuleSet) -> Option<Arc<Self>> {
+        static TABLES: OnceLock<Mutex<HashMap<RuleSet, Arc<SolvabilityTable>>>> = OnceLock::new();
+
+        if rules.card_count() == 0 || rules.card_count() > TABLE_MAX_DIGITS {
+            return None;
+        }
+        let operators = rules.allowed_operators();
+        if operators.intersection(OperatorSet::BASIC) != operators {
+            return None;
+        }
+
+        let mut tables = TABLES.get_or_init(Default::default).lock().unwrap();
+        let table = tables
+            .entry(rules.clone())
+            .or_insert_with(|| Arc::new(Self::build(rules)));
+        Some(Arc::clone(table))
+    }
+
+    /// すべての行
+    pub fn entries(&self) -> &[TableEntry] {
+        &self.entries
+    }
+
+    /// 組み合わせの数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 表が空か
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 数字の組み合わせに対応する行（並び順は問わない）
+    pub fn get(&self, digits: &[u8]) -> Option<&TableEntry> {
+        let mut sorted = digits.to_vec();
+        sorted.sort();
+        self.index.get(&sorted).map(|&i| &self.entries[i])
+    }
+
+    /// 目標の数を作れる行
+    pub fn solvable_entries(&self) -> impl Iterator<Item = &TableEntry> {
+        self.entries.iter().filter(|entry| entry.solvable)
+    }
+
+    /// 行の難易度（解けない場合は`None`）
+    pub fn difficulty<'a>(&self, entry: &'a TableEntry) -> Option<&'a Difficulty> {
+        entry
+            .difficulty
+            .get_or_init(|| {
+                if !entry.solvable {
+                    return None;
+                }
+                let numbers = GameNumbers::from_digits(entry.digits.clone());
+                Calculator::difficulty(&numbers, &self.rules)
+            })
+            .as_ref()
+    }
+
+    /// 行の本質的に異なる解の数
+    pub fn solution_count(&self, entry: &TableEntry) -> usize {
+        self.difficulty(entry)
+            .map_or(0, |difficulty| difficulty.distinct_solutions)
+    }
+}
+
+/// 昇順に並んだ数字の組み合わせをすべて列挙
+fn collect_multisets(
+    remaining: usize,
+    min_digit: u8,
+    digits: &mut Vec<u8>,
+    visit: &mut impl FnMut(&[u8]),
+) {
+    if remaining == 0 {
+        visit(digits);
+        return;
+    }
+
+    for digit in min_digit..=9 {
+        digits.push(digit);
+        collect_multisets(remaining - 1, digit, digits, visit);
+        digits.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::RuleVariant;
+
+    #[test]
+    fn test_table_covers_all_multisets() {
+        // テスト: 4枚なら715通りの組み合わせがある
+        let table = SolvabilityTable::for_rules(&RuleSet::default()).unwrap();
+        assert_eq!(table.len(), 715);
+        assert!(table.solvable_entries().count() > 500);
+    }
+
+    #[test]
+    fn test_lookup_ignores_order() {
+        // テスト: 並び順に関係なく同じ行が見つかる
+        let table = SolvabilityTable::for_rules(&RuleSet::default()).unwrap();
+        let entry = table.get(&[8, 5, 1, 1]).unwrap();
+        assert_eq!(entry.digits, vec![1, 1, 5, 8]);
+        assert!(entry.solvable);
+        assert_eq!(table.solution_count(entry), 1);
+        assert_eq!(table.difficulty(entry).unwrap().stars(), 5);
+
+        let entry = table.get(&[1, 1, 1, 1]).unwrap();
+        assert!(!entry.solvable);
+        assert_eq!(table.difficulty(entry), None);
+    }
+
+    #[test]
+    fn test_table_matches_solver() {
+        // テスト: 表の結果がソルバーの結果と一致する
+        let rules = RuleSet::with_target(24).with_digit_count(3);
+        let table = SolvabilityTable::build(&rules);
+        assert_eq!(table.len(), 220);
+        for entry in table.entries() {
+            let numbers = GameNumbers::from_digits(entry.digits.clone());
+            assert_eq!(
+                entry.solvable,
+                Calculator::can_make_target(&numbers, &rules)
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_digit_counts_have_no_table() {
+        // テスト: 枚数が多い場合は表を作らない
+        let rules = RuleSet::default().with_digit_count(TABLE_MAX_DIGITS + 1);
+        assert!(SolvabilityTable::for_rules(&rules).is_none());
+    }
+
+    #[test]
+    fn test_extended_operators_have_no_table() {
+        // テスト: 四則演算以外の演算子を使えるルールでは表を作らない
+        let rules = RuleSet::default().with_operators(OperatorSet::ALL);
+        assert!(SolvabilityTable::for_rules(&rules).is_none());
+
+        // 特別ルールで四則演算に絞られる場合は表を作る
+        let rules = rules.with_variant(RuleVariant::OnlyAddMul);
+        assert!(SolvabilityTable::for_rules(&rules).is_some());
+    }
+}
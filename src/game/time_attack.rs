@@ -0,0 +1,142 @@
+//! タイムアタックモード
+//!
+//! 制限時間内にできるだけ多くのステージをクリアする。
+//! クリアするたびに盤面の難易度に応じて残り時間が増える。
+
+use crate::game::Difficulty;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// タイムアタックの制限時間
+pub const TIME_ATTACK_DURATION: Duration = Duration::from_secs(120);
+
+/// クリアボーナスの星1つあたりの追加時間
+pub const TIME_BONUS_PER_STAR: Duration = Duration::from_secs(5);
+
+/// 進行中のタイムアタック
+#[derive(Debug, Clone, Default, Resource)]
+pub struct TimeAttack {
+    /// タイムアタック中か（通常モードではfalse）
+    pub active: bool,
+    /// 残り時間
+    pub remaining: Duration,
+    /// これまでに獲得したクリアボーナスの合計
+    pub bonus_total: Duration,
+}
+
+impl TimeAttack {
+    /// 制限時間を最初から数え直す（通常モードでは何もしない）
+    pub fn restart(&mut self) {
+        if self.active {
+            self.remaining = TIME_ATTACK_DURATION;
+            self.bonus_total = Duration::ZERO;
+        }
+    }
+
+    /// 経過時間だけ残り時間を減らす
+    ///
+    /// この呼び出しで時間切れになった場合のみ`true`を返す。
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        if !self.active || self.remaining.is_zero() {
+            return false;
+        }
+        self.remaining = self.remaining.saturating_sub(delta);
+        self.remaining.is_zero()
+    }
+
+    /// 時間切れになったか
+    pub fn is_time_up(&self) -> bool {
+        self.active && self.remaining.is_zero()
+    }
+
+    /// 盤面の難易度に応じたクリアボーナス（星の数 × 5秒）
+    pub fn bonus_for(difficulty: Option<&Difficulty>) -> Duration {
+        let stars = difficulty.map_or(1, Difficulty::stars);
+        TIME_BONUS_PER_STAR * u32::from(stars)
+    }
+
+    /// ステージをクリアしたときのボーナスを残り時間に加える
+    ///
+    /// 加えた時間を返す（通常モードでは0）。
+    pub fn add_clear_bonus(&mut self, difficulty: Option<&Difficulty>) -> Duration {
+        if !self.active {
+            return Duration::ZERO;
+        }
+        let bonus = Self::bonus_for(difficulty);
+        self.remaining += bonus;
+        self.bonus_total += bonus;
+        bonus
+    }
+
+    /// 残り時間の表示（"M:SS"、秒は切り上げ）
+    pub fn clock_text(&self) -> String {
+        let seconds = self.remaining.as_secs() + u64::from(self.remaining.subsec_nanos() > 0);
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Calculator, GameNumbers, RuleSet};
+
+    fn started() -> TimeAttack {
+        let mut time_attack = TimeAttack {
+            active: true,
+            ..default()
+        };
+        time_attack.restart();
+        time_attack
+    }
+
+    #[test]
+    fn test_countdown_reaches_zero_once() {
+        // テスト: 残り時間が0になった呼び出しでのみ時間切れを通知する
+        let mut time_attack = started();
+        assert_eq!(time_attack.clock_text(), "2:00");
+        assert!(!time_attack.tick(Duration::from_millis(500)));
+        assert_eq!(time_attack.clock_text(), "2:00");
+        assert!(!time_attack.tick(Duration::from_secs(60)));
+        assert_eq!(time_attack.clock_text(), "1:00");
+
+        assert!(time_attack.tick(Duration::from_secs(61)));
+        assert!(time_attack.is_time_up());
+        assert_eq!(time_attack.clock_text(), "0:00");
+        assert!(!time_attack.tick(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_inactive_clock_does_not_run() {
+        // テスト: 通常モードでは時間が減らず、ボーナスも付かない
+        let mut time_attack = TimeAttack::default();
+        time_attack.restart();
+        assert!(!time_attack.tick(Duration::from_secs(1000)));
+        assert!(!time_attack.is_time_up());
+        assert_eq!(time_attack.add_clear_bonus(None), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bonus_scales_with_difficulty() {
+        // テスト: 難しい盤面ほどクリアボーナスが大きい
+        let rules = RuleSet::default();
+        let easy = Calculator::difficulty(&GameNumbers::from_digits([1, 2, 3, 4]), &rules);
+        let hard = Calculator::difficulty(&GameNumbers::from_digits([1, 1, 5, 8]), &rules);
+        let easy_bonus = TimeAttack::bonus_for(easy.as_ref());
+        let hard_bonus = TimeAttack::bonus_for(hard.as_ref());
+        assert!(hard_bonus > easy_bonus);
+        assert_eq!(
+            hard_bonus,
+            TIME_BONUS_PER_STAR * u32::from(hard.as_ref().unwrap().stars())
+        );
+
+        let mut time_attack = started();
+        time_attack.tick(Duration::from_secs(100));
+        assert_eq!(time_attack.add_clear_bonus(easy.as_ref()), easy_bonus);
+        assert_eq!(time_attack.add_clear_bonus(hard.as_ref()), hard_bonus);
+        assert_eq!(
+            time_attack.remaining,
+            Duration::from_secs(20) + easy_bonus + hard_bonus
+        );
+        assert_eq!(time_attack.bonus_total, easy_bonus + hard_bonus);
+    }
+}
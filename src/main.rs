@@ -3,14 +3,19 @@ mod ui;
 
 use bevy::prelude::*;
 use bevy_inspector_egui::{bevy_egui::EguiPlugin, quick::WorldInspectorPlugin};
-use game::GameNumbers;
+use game::{GameNumbers, PuzzleRng, RuleSet};
 use ui::UIPlugin;
 
 fn main() {
+    let rules = RuleSet::default();
+    let mut rng = PuzzleRng::from_entropy();
+    let numbers = GameNumbers::generate_for_stage(&rules, 1, &mut rng)
+        .expect("the standard rules always have solvable boards");
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
-                title: "Make 10 Game".into(),
+                title: format!("{} Game", rules.title()),
                 resolution: (800.0, 600.0).into(),
                 ..default()
             }),
@@ -19,6 +24,8 @@ fn main() {
         .add_plugins(UIPlugin)
         .add_plugins(EguiPlugin::default())
         .add_plugins(WorldInspectorPlugin::new())
-        .insert_resource(GameNumbers::new()) // ゲーム用のリソースとして数字を追加
+        .insert_resource(numbers) // ゲーム用のリソースとして数字を追加
+        .insert_resource(rng)
+        .insert_resource(rules)
         .run();
 }
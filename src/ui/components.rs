@@ -1,3 +1,7 @@
+use crate::game::{
+    AnswerError, Arity, CalculationError, CardBoard, Expr, GameNumbers, Operator, Rational,
+    RuleSet, Token, write_tokens,
+};
 use bevy::prelude::*;
 
 // UIコンポーネント定義
@@ -5,16 +9,35 @@ use bevy::prelude::*;
 // 数字表示用のコンポーネント
 #[derive(Component)]
 pub struct NumberDisplay {
-    pub value: u32,
-    pub index: usize, // 0-3の数字のインデックス
+    pub value: Rational,
+    pub index: usize, // GameNumbers.digits内のインデックス
 }
 
+// 数字ボタンを並べるコンテナ
+#[derive(Component)]
+pub struct NumbersContainer;
+
 // 演算ボタン用のコンポーネント
 #[derive(Component)]
 pub struct OperatorButton {
-    pub operator: char, // '+', '-', '*', '/'
+    pub operator: Operator,
+    pub enabled: bool, // ルールで禁止されている、または使用済みの場合はfalse
+}
+
+// 演算子ボタンを並べるコンテナ（ルールで有効な演算子のみ）
+#[derive(Component)]
+pub struct OperatorsContainer;
+
+// 括弧ボタン用のコンポーネント
+#[derive(Component)]
+pub struct ParenthesisButton {
+    pub open: bool, // true: '(', false: ')'
 }
 
+// 直前の入力を取り消すボタン用のコンポーネント
+#[derive(Component)]
+pub struct BackspaceButton;
+
 // 現在の計算式表示用のコンポーネント
 #[derive(Component)]
 pub struct ExpressionDisplay;
@@ -23,6 +46,16 @@ pub struct ExpressionDisplay;
 #[derive(Component)]
 pub struct ResultDisplay;
 
+// 解答提出ボタン用のコンポーネント
+#[derive(Component)]
+pub struct SubmitButton;
+
+// 提出した解答が間違っていたときに計算式表示を揺らすコンポーネント
+#[derive(Component)]
+pub struct Shake {
+    pub timer: Timer,
+}
+
 // リセットボタン用のコンポーネント
 #[derive(Component)]
 pub struct ResetButton;
@@ -31,18 +64,344 @@ pub struct ResetButton;
 #[derive(Component)]
 pub struct ScoreDisplay;
 
+// 残りライフ表示用のコンポーネント
+#[derive(Component)]
+pub struct LivesDisplay;
+
+// タイムアタックの残り時間表示用のコンポーネント
+#[derive(Component)]
+pub struct TimeAttackDisplay;
+
+// 難易度表示用のコンポーネント
+#[derive(Component)]
+pub struct DifficultyDisplay;
+
+// 現在のルール表示用のコンポーネント
+#[derive(Component)]
+pub struct RuleDisplay;
+
+// ヒントボタン用のコンポーネント
+#[derive(Component)]
+pub struct HintButton;
+
+// ヒント表示用のコンポーネント
+#[derive(Component)]
+pub struct HintDisplay;
+
+// デイリーチャレンジ開始ボタン用のコンポーネント
+#[derive(Component)]
+pub struct DailyButton;
+
+// デイリーチャレンジの状況表示用のコンポーネント
+#[derive(Component)]
+pub struct DailyStatusDisplay;
+
+// タイトル表示用のコンポーネント
+#[derive(Component)]
+pub struct TitleDisplay;
+
+// 現在の盤面のパズルコード表示用のコンポーネント
+#[derive(Component)]
+pub struct PuzzleCodeDisplay;
+
+// パズルコード入力欄用のコンポーネント
+#[derive(Component)]
+pub struct CodeInputField;
+
+// パズルコード入力欄のテキスト用のコンポーネント
+#[derive(Component)]
+pub struct CodeInputText;
+
+// パズルコード読み込みボタン用のコンポーネント
+#[derive(Component)]
+pub struct LoadCodeButton;
+
 // ゲーム画面のメインコンテナ
 #[derive(Component)]
 pub struct GameScreenContainer;
 
+// プレイモード切り替えボタン用のコンポーネント
+#[derive(Component)]
+pub struct ModeToggleButton;
+
+// プレイモードを表すリソース
+#[derive(Resource, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum PlayMode {
+    /// 数字と演算子を左から順に入力して式を作る
+    #[default]
+    Expression,
+    /// 2枚のカードを選んで演算子を適用し、1枚にまとめていく
+    CardCombine,
+}
+
+impl PlayMode {
+    /// 表示用の名前
+    pub fn label(&self) -> &'static str {
+        match self {
+            PlayMode::Expression => "Expression",
+            PlayMode::CardCombine => "Cards",
+        }
+    }
+
+    /// もう一方のモード
+    pub fn toggled(&self) -> Self {
+        match self {
+            PlayMode::Expression => PlayMode::CardCombine,
+            PlayMode::CardCombine => PlayMode::Expression,
+        }
+    }
+}
+
 // 計算状態を管理するリソース
 #[derive(Resource, Reflect, Default)]
 #[reflect(Resource)]
 pub struct CalculationState {
-    pub expression: String,
-    pub result: Option<f64>,
-    pub selected_numbers: Vec<usize>, // 選択された数字のインデックス
-    pub operators: Vec<char>,         // 使用された演算子
+    #[reflect(ignore)]
+    pub tokens: Vec<Token>, // プレイヤーが入力したトークン列
+    #[reflect(ignore)]
+    pub expr: Option<Expr>, // 入力が完全な式になっている場合の構文木
+    #[reflect(ignore)]
+    pub board: CardBoard, // カード合成モードの盤面
+    pub result: Option<Rational>,
+    pub selected_numbers: Vec<usize>, // 使用済み（カード合成モードでは選択中）の数字のインデックス
+    #[reflect(ignore)]
+    pub operators: Vec<Operator>, // 使用された演算子
+    #[reflect(ignore)]
+    pub failure: Option<AnswerError>, // 直前に提出した解答が間違っていた理由
+}
+
+impl CalculationState {
+    /// トークンを追加し、式と計算結果を更新
+    ///
+    /// 途中の式が構文的に正しいままになり、数字のカードが未使用の場合のみ追加し、
+    /// 追加したかどうかを返す。
+    pub fn push_token(&mut self, token: Token) -> bool {
+        if !self.can_push(&token) {
+            return false;
+        }
+
+        if let Token::Literal { index, .. } = token {
+            self.selected_numbers.push(index);
+        }
+        self.tokens.push(token);
+        self.update_expr();
+        true
+    }
+
+    /// 最後のトークンを取り消す（数字の場合はカードを未使用に戻す）
+    pub fn pop_token(&mut self) -> Option<Token> {
+        let token = self.tokens.pop()?;
+        if let Token::Literal { index, .. } = token {
+            self.selected_numbers.retain(|&i| i != index);
+        }
+        self.update_expr();
+        Some(token)
+    }
+
+    /// トークンを追加しても途中の式として正しいか
+    pub fn can_push(&self, token: &Token) -> bool {
+        let last = self.tokens.last();
+        let after_operand = matches!(
+            last,
+            Some(Token::Literal { .. } | Token::RightParen | Token::Operator(Operator::Factorial))
+        );
+
+        match token {
+            // 同じカードは1回しか使えない
+            Token::Literal { index, .. } if self.selected_numbers.contains(index) => false,
+            // 数字・開き括弧・平方根は式の先頭、演算子や開き括弧の直後のみ
+            Token::Literal { .. } | Token::LeftParen | Token::Operator(Operator::Sqrt) => {
+                !after_operand
+            }
+            // 演算子（階乗を含む）は数字か閉じ括弧の直後のみ
+            Token::Operator(_) => after_operand,
+            // 閉じ括弧は対応する開き括弧があり、数字か閉じ括弧の直後のみ
+            Token::RightParen => after_operand && self.open_parens() > 0,
+        }
+    }
+
+    /// 完成した式がすべてのカードをちょうど1回ずつ使っているか
+    pub fn uses_all_cards(&self, numbers: &GameNumbers) -> bool {
+        self.expr
+            .as_ref()
+            .is_some_and(|expr| expr.uses_all_numbers(numbers))
+    }
+
+    /// 閉じられていない開き括弧の数
+    pub fn open_parens(&self) -> usize {
+        self.tokens.iter().fold(0, |depth, token| match token {
+            Token::LeftParen => depth + 1,
+            Token::RightParen => depth.saturating_sub(1),
+            _ => depth,
+        })
+    }
+
+    /// 入力中の式（カード合成モードでは盤面）を解答として判定する
+    ///
+    /// 判定結果は`failure`に記録され、入力を変更すると消える。
+    pub fn submit(
+        &mut self,
+        mode: PlayMode,
+        numbers: &GameNumbers,
+        rules: &RuleSet,
+    ) -> Result<(), AnswerError> {
+        let result = match mode {
+            PlayMode::Expression => Expr::from_tokens(&self.tokens)
+                .map_err(|_| AnswerError::Incomplete)
+                .and_then(|expr| rules.check_answer(&expr, numbers)),
+            // カード合成モードでは1枚にまとめ終わるまで提出できない
+            PlayMode::CardCombine => match self.board.result() {
+                Some(card) => rules.check_answer(&card.expr, numbers),
+                None => Err(AnswerError::NotAllNumbersUsed),
+            },
+        };
+        self.failure = result.as_ref().err().cloned();
+        result
+    }
+
+    /// 式が完成している場合のみ構文木と計算結果を設定
+    fn update_expr(&mut self) {
+        self.failure = None;
+        self.expr = Expr::from_tokens(&self.tokens).ok();
+        self.result = self.expr.as_ref().and_then(|expr| expr.evaluate().ok());
+    }
+
+    /// 入力をすべて消去し、カード合成の盤面を与えられた数字で作り直す
+    pub fn reset(&mut self, numbers: &GameNumbers) {
+        self.tokens.clear();
+        self.expr = None;
+        self.result = None;
+        self.board = CardBoard::new(numbers);
+        self.selected_numbers.clear();
+        self.operators.clear();
+        self.failure = None;
+    }
+
+    /// カードの選択を切り替える（3枚目を選ぶと最初の選択が外れる）
+    pub fn toggle_card(&mut self, index: usize) {
+        if let Some(pos) = self.selected_numbers.iter().position(|&i| i == index) {
+            self.selected_numbers.remove(pos);
+        } else {
+            if self.selected_numbers.len() == 2 {
+                self.selected_numbers.remove(0);
+            }
+            self.selected_numbers.push(index);
+        }
+    }
+
+    /// 選択中のカードに演算子を適用（二項演算子は2枚、単項演算子は1枚）
+    ///
+    /// 結果がルールで認められない値（負の数や分数）になる場合は盤面を変えない。
+    pub fn combine_selected(
+        &mut self,
+        op: Operator,
+        rules: &RuleSet,
+    ) -> Result<(), CalculationError> {
+        let index = match (op.arity(), &self.selected_numbers[..]) {
+            (Arity::Binary, &[first, second]) => {
+                self.board.combine(first, second, op)?;
+                // 2枚目が前にあった場合は取り除かれた分だけ結果の位置がずれる
+                if second < first { first - 1 } else { first }
+            }
+            (Arity::Unary, &[index]) => {
+                self.board.apply_unary(index, op)?;
+                index
+            }
+            _ => return Err(CalculationError::InvalidCardSelection),
+        };
+        if let Err(violation) = rules.check_intermediate(self.board.cards[index].value) {
+            self.board.undo();
+            return Err(violation.into());
+        }
+        self.operators.push(op);
+        self.selected_numbers.clear();
+        self.failure = None;
+
+        if let Some(card) = self.board.result() {
+            self.expr = Some(card.expr.clone());
+            self.result = Some(card.value);
+        }
+        Ok(())
+    }
+
+    /// 直前のカード合成を取り消す
+    pub fn undo_combine(&mut self) -> bool {
+        if !self.board.undo() {
+            return false;
+        }
+
+        self.operators.pop();
+        self.selected_numbers.clear();
+        self.failure = None;
+        self.expr = None;
+        self.result = None;
+        true
+    }
+
+    /// 入力済みの式（カード合成モードでは盤面）で使った演算子
+    pub fn used_operators(&self, mode: PlayMode) -> Vec<Operator> {
+        match mode {
+            PlayMode::Expression => self
+                .tokens
+                .iter()
+                .filter_map(|token| match token {
+                    Token::Operator(op) => Some(*op),
+                    _ => None,
+                })
+                .collect(),
+            PlayMode::CardCombine => self.operators.clone(),
+        }
+    }
+
+    /// 表示用の計算式
+    pub fn expression_text(&self, mode: PlayMode) -> String {
+        match mode {
+            PlayMode::Expression => {
+                let mut text = String::new();
+                let _ = write_tokens(&mut text, &self.tokens);
+                text
+            }
+            PlayMode::CardCombine => self
+                .board
+                .cards
+                .iter()
+                .map(|card| card.expr.to_string())
+                .collect::<Vec<_>>()
+                .join("  |  "),
+        }
+    }
+}
+
+// パズルコード入力欄の状態を管理するリソース
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct CodeInputState {
+    pub text: String,            // 入力中のコード
+    pub focused: bool,           // キーボード入力を受け付けているか
+    pub message: Option<String>, // 読み込みに失敗した理由
+}
+
+impl CodeInputState {
+    /// 入力できるコードの最大文字数（区切りを含む）
+    pub const MAX_LEN: usize = 24;
+
+    /// 1文字追加（大文字に揃え、最大文字数を超える分は無視）
+    pub fn push(&mut self, c: char) {
+        if self.text.len() < Self::MAX_LEN {
+            self.text.push(c.to_ascii_uppercase());
+        }
+    }
+
+    /// 入力欄に表示するテキスト
+    pub fn display_text(&self) -> String {
+        match (&self.message, self.focused, self.text.is_empty()) {
+            (Some(message), _, _) => message.clone(),
+            (None, true, _) => format!("{}_", self.text),
+            (None, false, true) => "Click to enter code".to_string(),
+            (None, false, false) => self.text.clone(),
+        }
+    }
 }
 
 // ステージクリアポップアップ関連のコンポーネント
@@ -54,3 +413,333 @@ pub struct NextStageButton;
 
 #[derive(Component)]
 pub struct PopupOverlay;
+
+// ギブアップボタン用のコンポーネント
+#[derive(Component)]
+pub struct GiveUpButton;
+
+// ギブアップ時の解答一覧ポップアップ
+#[derive(Component)]
+pub struct RevealPopup;
+
+// ギブアップ時の解答一覧（スクロールできるリスト）
+#[derive(Component)]
+pub struct RevealSolutionList;
+
+// メインメニューのゲーム開始ボタン
+#[derive(Component)]
+pub struct StartButton;
+
+// メインメニューのタイムアタック開始ボタン
+#[derive(Component)]
+pub struct TimeAttackButton;
+
+// メインメニューのハウスルール切り替えボタン
+#[derive(Component, Clone, Copy)]
+pub enum HouseRuleToggle {
+    // 演算子の有効・無効
+    Operator(Operator),
+    // 途中の計算結果を0以上に限る
+    NonNegativeIntermediates,
+    // 途中の計算結果を整数に限る
+    IntegerIntermediates,
+}
+
+// 一時停止メニュー関連のコンポーネント
+#[derive(Component)]
+pub struct ResumeButton;
+
+#[derive(Component)]
+pub struct MainMenuButton;
+
+// ゲームオーバー画面のリトライボタン
+#[derive(Component)]
+pub struct RetryButton;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::RuleViolation;
+
+    fn literal(index: usize, value: u8) -> Token {
+        Token::Literal { index, value }
+    }
+
+    #[test]
+    fn test_parenthesized_expression() {
+        // テスト: (1 + 4) * (9 - 7) を入力できる
+        let mut state = CalculationState::default();
+        let tokens = [
+            Token::LeftParen,
+            literal(0, 1),
+            Token::Operator(Operator::Add),
+            literal(1, 4),
+            Token::RightParen,
+            Token::Operator(Operator::Mul),
+            Token::LeftParen,
+            literal(2, 9),
+            Token::Operator(Operator::Sub),
+            literal(3, 7),
+        ];
+        for token in tokens {
+            assert!(state.push_token(token));
+        }
+
+        // 括弧が閉じていない間は結果を表示しない
+        assert_eq!(state.open_parens(), 1);
+        assert_eq!(state.result, None);
+
+        assert!(state.push_token(Token::RightParen));
+        assert_eq!(state.result, Some(Rational::from_integer(10)));
+        assert_eq!(
+            state.expression_text(PlayMode::Expression),
+            "(1 + 4) * (9 - 7)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_tokens_are_rejected() {
+        // テスト: 構文が崩れるトークンは追加されない
+        let mut state = CalculationState::default();
+        assert!(!state.push_token(Token::Operator(Operator::Add)));
+        assert!(!state.push_token(Token::RightParen));
+        assert!(state.push_token(literal(0, 1)));
+        assert!(!state.push_token(literal(1, 2)));
+        assert!(!state.push_token(Token::LeftParen));
+        assert!(!state.push_token(Token::RightParen));
+        assert_eq!(state.tokens, vec![literal(0, 1)]);
+    }
+
+    #[test]
+    fn test_result_only_when_complete() {
+        // テスト: 演算子で終わる途中の式では結果を表示しない
+        let mut state = CalculationState::default();
+        state.push_token(literal(0, 2));
+        assert_eq!(state.result, Some(Rational::from_integer(2)));
+        state.push_token(Token::Operator(Operator::Mul));
+        assert_eq!(state.result, None);
+        state.push_token(literal(1, 5));
+        assert_eq!(state.result, Some(Rational::from_integer(10)));
+    }
+
+    #[test]
+    fn test_card_can_only_be_used_once() {
+        // テスト: 同じカードを2回使うことはできない
+        let mut state = CalculationState::default();
+        assert!(state.push_token(literal(0, 9)));
+        assert!(state.push_token(Token::Operator(Operator::Add)));
+        assert!(!state.push_token(literal(0, 9)));
+        assert_eq!(state.selected_numbers, vec![0]);
+
+        // 取り消すと再び使えるようになる
+        state.pop_token();
+        state.pop_token();
+        assert!(state.selected_numbers.is_empty());
+        assert!(state.push_token(literal(0, 9)));
+    }
+
+    #[test]
+    fn test_uses_all_cards_required() {
+        // テスト: [1, 9, 2, 5]で 1 + 9 はクリアにならず、(9 - 5 + 1) * 2 はクリアになる
+        let numbers = GameNumbers::from_digits([1, 9, 2, 5]);
+        let mut state = CalculationState::default();
+        state.push_token(literal(0, 1));
+        state.push_token(Token::Operator(Operator::Add));
+        state.push_token(literal(1, 9));
+        assert_eq!(state.result, Some(Rational::from_integer(10)));
+        assert!(!state.uses_all_cards(&numbers));
+
+        state.reset(&numbers);
+        for token in [
+            Token::LeftParen,
+            literal(1, 9),
+            Token::Operator(Operator::Sub),
+            literal(3, 5),
+            Token::Operator(Operator::Add),
+            literal(0, 1),
+            Token::RightParen,
+            Token::Operator(Operator::Mul),
+            literal(2, 2),
+        ] {
+            assert!(state.push_token(token));
+        }
+        assert_eq!(state.result, Some(Rational::from_integer(10)));
+        assert!(state.uses_all_cards(&numbers));
+    }
+
+    #[test]
+    fn test_pop_token_restores_previous_state() {
+        // テスト: バックスペースで直前の状態に戻る
+        let mut state = CalculationState::default();
+        state.push_token(Token::LeftParen);
+        state.push_token(literal(0, 3));
+        state.push_token(Token::RightParen);
+        assert_eq!(state.result, Some(Rational::from_integer(3)));
+
+        assert_eq!(state.pop_token(), Some(Token::RightParen));
+        assert_eq!(state.result, None);
+        assert_eq!(state.open_parens(), 1);
+
+        state.pop_token();
+        state.pop_token();
+        assert_eq!(state.pop_token(), None);
+        assert!(state.tokens.is_empty());
+    }
+
+    #[test]
+    fn test_unary_operator_tokens() {
+        // テスト: 平方根は数字の前、階乗は数字の後ろにだけ置ける
+        let mut state = CalculationState::default();
+        assert!(!state.push_token(Token::Operator(Operator::Factorial)));
+        assert!(state.push_token(Token::Operator(Operator::Sqrt)));
+        assert!(state.push_token(literal(0, 9)));
+        assert!(!state.push_token(Token::Operator(Operator::Sqrt)));
+        assert!(state.push_token(Token::Operator(Operator::Add)));
+        assert!(state.push_token(literal(1, 3)));
+        assert!(state.push_token(Token::Operator(Operator::Factorial)));
+        assert!(!state.push_token(literal(2, 1)));
+        assert!(state.push_token(Token::Operator(Operator::Add)));
+        assert!(state.push_token(literal(2, 1)));
+
+        assert_eq!(state.result, Some(Rational::from_integer(10)));
+        assert_eq!(
+            state.expression_text(PlayMode::Expression),
+            "sqrt 9 + 3! + 1"
+        );
+    }
+
+    #[test]
+    fn test_combine_selected_with_unary_operator() {
+        // テスト: カード合成モードで単項演算子は1枚だけ選んで適用する
+        let numbers = GameNumbers::from_digits([3, 4]);
+        let mut state = CalculationState::default();
+        state.reset(&numbers);
+
+        state.toggle_card(0);
+        state.toggle_card(1);
+        assert_eq!(
+            state.combine_selected(Operator::Factorial, &RuleSet::default()),
+            Err(CalculationError::InvalidCardSelection)
+        );
+
+        state.toggle_card(1);
+        state
+            .combine_selected(Operator::Factorial, &RuleSet::default())
+            .unwrap();
+        state.toggle_card(0);
+        state.toggle_card(1);
+        state
+            .combine_selected(Operator::Add, &RuleSet::default())
+            .unwrap();
+        assert_eq!(state.result, Some(Rational::from_integer(10)));
+        assert_eq!(state.operators, vec![Operator::Factorial, Operator::Add]);
+    }
+
+    #[test]
+    fn test_used_operators() {
+        // テスト: 入力済みの演算子を使用済みとして数える
+        let mut state = CalculationState::default();
+        for token in [
+            literal(0, 9),
+            Token::Operator(Operator::Sub),
+            literal(1, 5),
+            Token::Operator(Operator::Mul),
+            literal(2, 2),
+        ] {
+            state.push_token(token);
+        }
+        assert_eq!(
+            state.used_operators(PlayMode::Expression),
+            vec![Operator::Sub, Operator::Mul]
+        );
+        assert!(state.used_operators(PlayMode::CardCombine).is_empty());
+    }
+
+    #[test]
+    fn test_combine_selected_honors_intermediate_rules() {
+        // テスト: 整数のみのルールでは 1 / 5 のカードを作れない
+        let numbers = GameNumbers::from_digits([1, 1, 5, 8]);
+        let rules = RuleSet::default().with_integer_intermediates(true);
+        let mut state = CalculationState::default();
+        state.reset(&numbers);
+        let before = state.board.clone();
+
+        state.toggle_card(1);
+        state.toggle_card(2);
+        assert_eq!(
+            state.combine_selected(Operator::Div, &rules),
+            Err(CalculationError::RuleViolation(
+                RuleViolation::FractionalIntermediate(Rational::new(1, 5).unwrap())
+            ))
+        );
+        assert_eq!(state.board, before);
+        assert!(state.operators.is_empty());
+
+        // 後ろのカードを1枚目に選んでも結果の位置を正しく検証する
+        let rules = RuleSet::default().with_non_negative_intermediates(true);
+        state.selected_numbers = vec![3, 0];
+        state.combine_selected(Operator::Sub, &rules).unwrap(); // [1, 5, 7]
+        assert_eq!(state.board.cards[2].value, Rational::from_integer(7));
+        state.selected_numbers = vec![0, 1];
+        assert!(state.combine_selected(Operator::Sub, &rules).is_err());
+    }
+
+    #[test]
+    fn test_submit_reports_failure_until_input_changes() {
+        // テスト: 提出した解答が間違っている理由を記録し、入力を変えると消える
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let rules = RuleSet::default();
+        let mut state = CalculationState::default();
+        state.reset(&numbers);
+
+        assert_eq!(
+            state.submit(PlayMode::Expression, &numbers, &rules),
+            Err(AnswerError::Incomplete)
+        );
+        for (i, op) in [Operator::Add, Operator::Add, Operator::Mul]
+            .into_iter()
+            .enumerate()
+        {
+            state.push_token(literal(i, numbers.digits[i]));
+            state.push_token(Token::Operator(op));
+        }
+        state.push_token(literal(3, 4));
+        assert_eq!(
+            state.submit(PlayMode::Expression, &numbers, &rules),
+            Err(AnswerError::WrongResult(Rational::from_integer(15)))
+        );
+        assert!(state.failure.is_some());
+
+        state.pop_token();
+        assert_eq!(state.failure, None);
+        state.tokens[5] = Token::Operator(Operator::Add);
+        state.push_token(literal(3, 4));
+        assert_eq!(state.submit(PlayMode::Expression, &numbers, &rules), Ok(()));
+        assert_eq!(state.failure, None);
+    }
+
+    #[test]
+    fn test_submit_in_card_mode_requires_single_card() {
+        // テスト: カード合成モードでは1枚にまとめるまで正解にならない
+        let numbers = GameNumbers::from_digits([1, 2, 3, 4]);
+        let rules = RuleSet::default();
+        let mut state = CalculationState::default();
+        state.reset(&numbers);
+
+        state.selected_numbers = vec![0, 1];
+        state.combine_selected(Operator::Add, &rules).unwrap(); // [3, 3, 4]
+        assert_eq!(
+            state.submit(PlayMode::CardCombine, &numbers, &rules),
+            Err(AnswerError::NotAllNumbersUsed)
+        );
+
+        state.selected_numbers = vec![0, 1];
+        state.combine_selected(Operator::Add, &rules).unwrap(); // [6, 4]
+        state.selected_numbers = vec![0, 1];
+        state.combine_selected(Operator::Add, &rules).unwrap(); // [10]
+        assert_eq!(
+            state.submit(PlayMode::CardCombine, &numbers, &rules),
+            Ok(())
+        );
+    }
+}
@@ -1,57 +1,113 @@
 #[cfg(test)]
 mod tests {
-    use super::super::systems::evaluate_expression;
+    use crate::game::{CalculationError, Expr, GameNumbers, Rational};
+
+    // 入力された式を解析して評価する
+    fn evaluate_expression(expression: &str) -> Option<Rational> {
+        Expr::parse(expression).ok()?.evaluate().ok()
+    }
 
     // 基本的な2項演算のテスト
     #[test]
     fn test_basic_addition() {
-        assert_eq!(evaluate_expression("2 + 3"), Some(5.0));
-        assert_eq!(evaluate_expression("1 + 9"), Some(10.0));
+        assert_eq!(
+            evaluate_expression("2 + 3"),
+            Some(Rational::from_integer(5))
+        );
+        assert_eq!(
+            evaluate_expression("1 + 9"),
+            Some(Rational::from_integer(10))
+        );
     }
 
     #[test]
     fn test_basic_subtraction() {
-        assert_eq!(evaluate_expression("5 - 2"), Some(3.0));
-        assert_eq!(evaluate_expression("9 - 4"), Some(5.0));
+        assert_eq!(
+            evaluate_expression("5 - 2"),
+            Some(Rational::from_integer(3))
+        );
+        assert_eq!(
+            evaluate_expression("9 - 4"),
+            Some(Rational::from_integer(5))
+        );
     }
 
     #[test]
     fn test_basic_multiplication() {
-        assert_eq!(evaluate_expression("3 * 4"), Some(12.0));
-        assert_eq!(evaluate_expression("2 * 5"), Some(10.0));
+        assert_eq!(
+            evaluate_expression("3 * 4"),
+            Some(Rational::from_integer(12))
+        );
+        assert_eq!(
+            evaluate_expression("2 * 5"),
+            Some(Rational::from_integer(10))
+        );
     }
 
     #[test]
     fn test_basic_division() {
-        assert_eq!(evaluate_expression("8 / 2"), Some(4.0));
-        assert_eq!(evaluate_expression("9 / 3"), Some(3.0));
+        assert_eq!(
+            evaluate_expression("8 / 2"),
+            Some(Rational::from_integer(4))
+        );
+        assert_eq!(
+            evaluate_expression("9 / 3"),
+            Some(Rational::from_integer(3))
+        );
     }
 
     // 演算子優先度のテスト
     #[test]
     fn test_operator_precedence_multiply_first() {
-        assert_eq!(evaluate_expression("2 + 3 * 4"), Some(14.0)); // 2 + (3 * 4) = 2 + 12 = 14
-        assert_eq!(evaluate_expression("1 + 2 * 3"), Some(7.0)); // 1 + (2 * 3) = 1 + 6 = 7
+        assert_eq!(
+            evaluate_expression("2 + 3 * 4"),
+            Some(Rational::from_integer(14))
+        ); // 2 + (3 * 4) = 2 + 12 = 14
+        assert_eq!(
+            evaluate_expression("1 + 2 * 3"),
+            Some(Rational::from_integer(7))
+        ); // 1 + (2 * 3) = 1 + 6 = 7
     }
 
     #[test]
     fn test_operator_precedence_divide_first() {
-        assert_eq!(evaluate_expression("8 + 6 / 2"), Some(11.0)); // 8 + (6 / 2) = 8 + 3 = 11
-        assert_eq!(evaluate_expression("1 + 8 / 4"), Some(3.0)); // 1 + (8 / 4) = 1 + 2 = 3
+        assert_eq!(
+            evaluate_expression("8 + 6 / 2"),
+            Some(Rational::from_integer(11))
+        ); // 8 + (6 / 2) = 8 + 3 = 11
+        assert_eq!(
+            evaluate_expression("1 + 8 / 4"),
+            Some(Rational::from_integer(3))
+        ); // 1 + (8 / 4) = 1 + 2 = 3
     }
 
     #[test]
     fn test_operator_precedence_mixed() {
-        assert_eq!(evaluate_expression("2 + 3 * 4 - 1"), Some(13.0)); // 2 + (3 * 4) - 1 = 2 + 12 - 1 = 13
-        assert_eq!(evaluate_expression("9 - 6 / 2 + 1"), Some(7.0)); // 9 - (6 / 2) + 1 = 9 - 3 + 1 = 7
+        assert_eq!(
+            evaluate_expression("2 + 3 * 4 - 1"),
+            Some(Rational::from_integer(13))
+        ); // 2 + (3 * 4) - 1 = 2 + 12 - 1 = 13
+        assert_eq!(
+            evaluate_expression("9 - 6 / 2 + 1"),
+            Some(Rational::from_integer(7))
+        ); // 9 - (6 / 2) + 1 = 9 - 3 + 1 = 7
     }
 
     // 4項演算のテスト
     #[test]
     fn test_four_operand_expressions() {
-        assert_eq!(evaluate_expression("1 + 2 + 3 + 4"), Some(10.0));
-        assert_eq!(evaluate_expression("2 * 3 + 4 / 2"), Some(8.0)); // (2 * 3) + (4 / 2) = 6 + 2 = 8
-        assert_eq!(evaluate_expression("9 - 3 + 2 * 2"), Some(10.0)); // 9 - 3 + (2 * 2) = 9 - 3 + 4 = 10
+        assert_eq!(
+            evaluate_expression("1 + 2 + 3 + 4"),
+            Some(Rational::from_integer(10))
+        );
+        assert_eq!(
+            evaluate_expression("2 * 3 + 4 / 2"),
+            Some(Rational::from_integer(8))
+        ); // (2 * 3) + (4 / 2) = 6 + 2 = 8
+        assert_eq!(
+            evaluate_expression("9 - 3 + 2 * 2"),
+            Some(Rational::from_integer(10))
+        ); // 9 - 3 + (2 * 2) = 9 - 3 + 4 = 10
     }
 
     // ゼロ除算のテスト
@@ -64,7 +120,7 @@ mod tests {
     // 無効な入力のテスト
     #[test]
     fn test_invalid_short_expressions() {
-        assert_eq!(evaluate_expression("1"), None);
+        assert_eq!(evaluate_expression("1"), Some(Rational::from_integer(1))); // 数字単体は有効な式
         assert_eq!(evaluate_expression("1 +"), None);
         assert_eq!(evaluate_expression("+ 2"), None);
         assert_eq!(evaluate_expression(""), None);
@@ -79,8 +135,16 @@ mod tests {
 
     #[test]
     fn test_invalid_zero_numbers() {
-        assert_eq!(evaluate_expression("0 + 5"), None); // 0は無効
-        assert_eq!(evaluate_expression("1 + 0"), None); // 0は無効
+        // 0はカードにない限り使えない
+        let numbers = GameNumbers::from_digits([1, 5, 2, 3]);
+        assert_eq!(
+            Expr::parse_with_numbers("0 + 5", &numbers),
+            Err(CalculationError::DigitNotAvailable(0))
+        );
+        assert_eq!(
+            Expr::parse_with_numbers("1 + 0", &numbers),
+            Err(CalculationError::DigitNotAvailable(0))
+        );
     }
 
     #[test]
@@ -91,15 +155,18 @@ mod tests {
 
     #[test]
     fn test_invalid_operators() {
-        assert_eq!(evaluate_expression("1 & 2"), None); // 無効な演算子
-        assert_eq!(evaluate_expression("1 ^ 2"), None); // 無効な演算子
+        assert_eq!(evaluate_expression("1 $ 2"), None); // 無効な演算子
+        assert_eq!(evaluate_expression("1 # 2"), None); // 無効な演算子
         assert_eq!(evaluate_expression("1 % 2"), None); // 無効な演算子
     }
 
     #[test]
     fn test_invalid_consecutive_operators() {
         assert_eq!(evaluate_expression("1 + + 2"), None); // 連続する演算子
-        assert_eq!(evaluate_expression("1 * - 2"), None); // 連続する演算子
+        assert_eq!(
+            evaluate_expression("1 * - 2"),
+            Some(Rational::from_integer(-2))
+        ); // 単項マイナス
         assert_eq!(evaluate_expression("1 + * 2"), None); // 連続する演算子
     }
 
@@ -118,27 +185,60 @@ mod tests {
     // Make 10 の典型的なケースのテスト
     #[test]
     fn test_make_10_cases() {
-        assert_eq!(evaluate_expression("1 + 2 + 3 + 4"), Some(10.0));
-        assert_eq!(evaluate_expression("2 * 5"), Some(10.0));
-        assert_eq!(evaluate_expression("3 + 4 + 2 + 1"), Some(10.0));
-        assert_eq!(evaluate_expression("6 + 8 / 2"), Some(10.0)); // 6 + (8 / 2) = 6 + 4 = 10
-        assert_eq!(evaluate_expression("4 * 3 - 2"), Some(10.0)); // (4 * 3) - 2 = 12 - 2 = 10
+        assert_eq!(
+            evaluate_expression("1 + 2 + 3 + 4"),
+            Some(Rational::from_integer(10))
+        );
+        assert_eq!(
+            evaluate_expression("2 * 5"),
+            Some(Rational::from_integer(10))
+        );
+        assert_eq!(
+            evaluate_expression("3 + 4 + 2 + 1"),
+            Some(Rational::from_integer(10))
+        );
+        assert_eq!(
+            evaluate_expression("6 + 8 / 2"),
+            Some(Rational::from_integer(10))
+        ); // 6 + (8 / 2) = 6 + 4 = 10
+        assert_eq!(
+            evaluate_expression("4 * 3 - 2"),
+            Some(Rational::from_integer(10))
+        ); // (4 * 3) - 2 = 12 - 2 = 10
     }
 
     // エッジケースのテスト
     #[test]
     fn test_edge_cases() {
-        assert_eq!(evaluate_expression("9 * 1"), Some(9.0));
-        assert_eq!(evaluate_expression("1 * 9"), Some(9.0));
-        assert_eq!(evaluate_expression("9 / 9"), Some(1.0));
-        assert_eq!(evaluate_expression("9 - 9"), Some(0.0));
-        assert_eq!(evaluate_expression("1 + 1 + 1 + 1"), Some(4.0));
+        assert_eq!(
+            evaluate_expression("9 * 1"),
+            Some(Rational::from_integer(9))
+        );
+        assert_eq!(
+            evaluate_expression("1 * 9"),
+            Some(Rational::from_integer(9))
+        );
+        assert_eq!(
+            evaluate_expression("9 / 9"),
+            Some(Rational::from_integer(1))
+        );
+        assert_eq!(
+            evaluate_expression("9 - 9"),
+            Some(Rational::from_integer(0))
+        );
+        assert_eq!(
+            evaluate_expression("1 + 1 + 1 + 1"),
+            Some(Rational::from_integer(4))
+        );
     }
 
     // 空白の処理テスト
     #[test]
     fn test_whitespace_handling() {
-        assert_eq!(evaluate_expression("  2  +  3  "), Some(5.0));
-        assert_eq!(evaluate_expression("2+3"), None); // 空白なしは無効（現在の実装では）
+        assert_eq!(
+            evaluate_expression("  2  +  3  "),
+            Some(Rational::from_integer(5))
+        );
+        assert_eq!(evaluate_expression("2+3"), Some(Rational::from_integer(5))); // 空白なしも有効
     }
 }
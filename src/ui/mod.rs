@@ -2,10 +2,16 @@ pub mod components;
 #[cfg(test)]
 mod expression_tests;
 pub mod systems;
+#[cfg(test)]
+mod time_attack_tests;
 
-use crate::game::state::{GameProgress, GameState};
+use crate::game::state::{GameProgress, GameState, InGame};
+use crate::game::{
+    BoardAnalysis, DAILY_RECORDS_PATH, DailyChallenge, DailyRecords, HouseRules, PuzzleRng,
+    RuleSet, TimeAttack,
+};
 use bevy::prelude::*;
-use components::CalculationState;
+use components::{CalculationState, CodeInputState, PlayMode};
 
 // UIプラグイン
 pub struct UIPlugin;
@@ -13,20 +19,127 @@ pub struct UIPlugin;
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<CalculationState>()
+            .register_type::<PlayMode>()
+            .register_type::<CodeInputState>()
             .init_resource::<CalculationState>()
-            .init_resource::<GameState>()
+            .init_resource::<PlayMode>()
+            .init_resource::<CodeInputState>()
             .init_resource::<GameProgress>()
-            .add_systems(Startup, systems::setup_ui)
+            .init_resource::<RuleSet>()
+            .init_resource::<HouseRules>()
+            .init_resource::<PuzzleRng>()
+            .init_resource::<DailyChallenge>()
+            .init_resource::<TimeAttack>()
+            .init_resource::<BoardAnalysis>()
+            .insert_resource(DailyRecords::load(DAILY_RECORDS_PATH))
+            .init_state::<GameState>()
+            .add_computed_state::<InGame>()
+            .enable_state_scoped_entities::<InGame>()
+            .add_systems(Startup, systems::setup_camera)
+            // 各状態に入ったときに、その状態で表示するUIを生成する
+            .add_systems(OnEnter(GameState::MainMenu), systems::setup_main_menu)
+            // メインメニューから始めるときとリトライするときは新しいゲームにする
+            .add_systems(OnExit(GameState::MainMenu), systems::start_new_game)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::GameOver,
+                    entered: GameState::Playing,
+                },
+                systems::start_new_game,
+            )
+            .add_systems(
+                OnTransition {
+                    exited: GameState::TimeUp,
+                    entered: GameState::Playing,
+                },
+                systems::start_new_game,
+            )
+            .add_systems(OnEnter(InGame), systems::setup_ui)
+            .add_systems(OnEnter(GameState::Paused), systems::setup_pause_menu)
+            .add_systems(OnExit(GameState::Paused), systems::resume_time)
+            .add_systems(
+                OnEnter(GameState::StageClear),
+                systems::spawn_stage_clear_popup,
+            )
+            .add_systems(OnEnter(GameState::GaveUp), systems::spawn_reveal_popup)
+            .add_systems(
+                Update,
+                systems::reveal_scroll_system.run_if(in_state(GameState::GaveUp)),
+            )
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                systems::setup_game_over_screen,
+            )
+            .add_systems(OnEnter(GameState::TimeUp), systems::setup_time_up_screen)
+            .add_systems(
+                Update,
+                (systems::main_menu_system, systems::house_rule_toggle_system)
+                    .run_if(in_state(GameState::MainMenu)),
+            )
+            // ゲーム画面の表示はポップアップや一時停止中も更新する
             .add_systems(
                 Update,
                 (
-                    systems::button_system,
                     systems::number_display_system,
+                    systems::operator_buttons_system,
+                    systems::play_mode_display_system,
                     systems::calculation_display_system,
-                    systems::stage_clear_detection_system,
-                    systems::popup_system,
                     systems::game_info_display_system,
-                ),
+                    systems::lives_display_system,
+                    systems::time_attack_display_system,
+                    systems::difficulty_display_system,
+                    systems::rule_display_system,
+                    systems::hint_display_system,
+                    systems::daily_status_display_system,
+                    systems::puzzle_code_display_system,
+                    systems::shake_feedback_system,
+                )
+                    .run_if(in_state(InGame)),
+            )
+            // 盤面の解析は、解析結果を使う表示や操作より先に行う
+            .add_systems(
+                Update,
+                systems::board_analysis_system
+                    .before(systems::difficulty_display_system)
+                    .before(systems::hint_display_system)
+                    .before(systems::submit_system)
+                    .before(systems::hint_button_system)
+                    .run_if(in_state(InGame)),
+            )
+            // 盤面の操作はプレイ中のみ受け付ける
+            .add_systems(
+                Update,
+                (
+                    systems::button_system,
+                    systems::submit_system.before(systems::code_input_system),
+                    systems::daily_button_system,
+                    systems::hint_button_system,
+                    systems::give_up_button_system,
+                    systems::code_input_system,
+                    // タイムアタックの時計はポップアップや一時停止中は止まる
+                    systems::time_attack_clock_system,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                systems::pause_input_system
+                    .before(systems::code_input_system)
+                    .run_if(in_state(GameState::Playing).or(in_state(GameState::Paused))),
+            )
+            .add_systems(
+                Update,
+                systems::pause_menu_system.run_if(in_state(GameState::Paused)),
+            )
+            .add_systems(
+                Update,
+                systems::game_over_menu_system
+                    .run_if(in_state(GameState::GameOver).or(in_state(GameState::TimeUp))),
+            )
+            .add_systems(
+                Update,
+                systems::popup_system
+                    .run_if(in_state(GameState::StageClear).or(in_state(GameState::GaveUp))),
             );
     }
 }
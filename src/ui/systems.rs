@@ -1,6 +1,13 @@
 use super::components::*;
-use crate::game::GameNumbers;
-use crate::game::state::{GameProgress, GameState};
+use crate::game::state::{GIVE_UP_PENALTY, GameProgress, GameState, InGame, STARTING_LIVES};
+use crate::game::{
+    BoardAnalysis, Calculator, DAILY_PUZZLE_COUNT, DAILY_RECORDS_PATH, DailyChallenge, DailyDate,
+    DailyRecords, Expr, GameNumbers, HINT_COST, HouseRules, MAX_HINT_LEVEL, Operator, PuzzleRng,
+    Rational, RuleSet, RuleVariant, TimeAttack, Token,
+};
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 
 type ButtonQuery<'w, 's> = Query<
@@ -12,6 +19,9 @@ type ButtonQuery<'w, 's> = Query<
         Option<&'static NumberDisplay>,
         Option<&'static OperatorButton>,
         Option<&'static ResetButton>,
+        Option<&'static ModeToggleButton>,
+        Option<&'static ParenthesisButton>,
+        Option<&'static BackspaceButton>,
     ),
     (Changed<Interaction>, With<Button>),
 >;
@@ -28,12 +38,644 @@ type PopupInteractionQuery<'w, 's> = Query<
     (Changed<Interaction>, With<Button>),
 >;
 
-// UI初期化システム
-pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
-    // カメラの作成
+// デイリーボタン用のクエリ型を定義
+type DailyButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<DailyButton>),
+>;
+
+// ギブアップボタン用のクエリ型を定義
+type GiveUpButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<GiveUpButton>),
+>;
+
+// ヒントボタン用のクエリ型を定義
+type HintButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<HintButton>),
+>;
+
+// パズルコード入力欄用のクエリ型を定義
+type CodeInputQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        Option<&'static CodeInputField>,
+    ),
+    (
+        Changed<Interaction>,
+        Or<(With<CodeInputField>, With<LoadCodeButton>)>,
+    ),
+>;
+
+// メインメニューのゲーム開始ボタン用のクエリ型を定義
+type StartButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        Option<&'static TimeAttackButton>,
+    ),
+    (
+        Changed<Interaction>,
+        Or<(With<StartButton>, With<TimeAttackButton>)>,
+    ),
+>;
+
+// ハウスルール切り替えボタン用のクエリ型を定義
+type HouseRuleToggleQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        &'static HouseRuleToggle,
+    ),
+    Changed<Interaction>,
+>;
+
+// 一時停止メニューのボタン用のクエリ型を定義
+type PauseMenuQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        Option<&'static ResumeButton>,
+    ),
+    (
+        Changed<Interaction>,
+        Or<(With<ResumeButton>, With<MainMenuButton>)>,
+    ),
+>;
+
+// ゲームオーバー画面のボタン用のクエリ型を定義
+type GameOverMenuQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        Option<&'static RetryButton>,
+    ),
+    (
+        Changed<Interaction>,
+        Or<(With<RetryButton>, With<MainMenuButton>)>,
+    ),
+>;
+
+// 解答提出ボタン用のクエリ型を定義
+type SubmitButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<SubmitButton>),
+>;
+
+// 解答提出ボタンの色
+const SUBMIT_BUTTON_COLOR: Color = Color::srgb(0.2, 0.5, 0.3);
+// 計算式表示の通常時の色
+const EXPRESSION_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+// 間違った解答を提出したときの計算式表示の色
+const FAILURE_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+// 間違った解答を提出したときに計算式表示を揺らす時間（秒）
+const SHAKE_DURATION: f32 = 0.5;
+// 揺れの幅（ピクセル）と速さ（ラジアン毎秒）
+const SHAKE_AMPLITUDE: f32 = 8.0;
+const SHAKE_FREQUENCY: f32 = 60.0;
+
+// メニューボタンの色
+const MENU_BUTTON_COLOR: Color = Color::srgb(0.2, 0.4, 0.6);
+// カーソルが乗っているメニューボタンの色
+const MENU_BUTTON_HOVERED_COLOR: Color = Color::srgb(0.3, 0.5, 0.7);
+// 有効にしたハウスルールのボタンの色
+const HOUSE_RULE_ON_COLOR: Color = Color::srgb(0.2, 0.55, 0.35);
+// 無効のハウスルールのボタンの色
+const HOUSE_RULE_OFF_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+
+// パズルコード入力欄の色
+const CODE_INPUT_COLOR: Color = Color::srgb(0.2, 0.2, 0.25);
+// 入力中のパズルコード入力欄の色
+const CODE_INPUT_FOCUSED_COLOR: Color = Color::srgb(0.3, 0.3, 0.4);
+
+// 数字ボタンの通常時の色
+const NUMBER_BUTTON_COLOR: Color = Color::srgb(0.3, 0.5, 0.7);
+// カード合成モードで選択中のカードの色
+const SELECTED_CARD_COLOR: Color = Color::srgb(0.8, 0.6, 0.2);
+// 式入力モードで使用済みのカードの色
+const USED_CARD_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+
+// 演算子ボタンの通常時の色
+const OPERATOR_BUTTON_COLOR: Color = Color::srgb(0.5, 0.3, 0.7);
+// ルールで使えない演算子ボタンの色
+const DISABLED_OPERATOR_COLOR: Color = Color::srgb(0.25, 0.2, 0.3);
+
+// ギブアップ時の解の一覧の高さ（ピクセル、これを超える分はスクロールする）
+const REVEAL_LIST_HEIGHT: f32 = 320.0;
+// マウスホイール1行分のスクロール量（ピクセル）
+const SCROLL_LINE_HEIGHT: f32 = 24.0;
+
+// カメラ初期化システム
+pub fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2d);
+}
+
+// メインメニュー生成システム
+pub fn setup_main_menu(mut commands: Commands, rules: Res<RuleSet>, house_rules: Res<HouseRules>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(40.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.1, 0.1, 0.1)),
+            StateScoped(GameState::MainMenu),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(format!("{} Game", rules.title())),
+                TextFont {
+                    font_size: 64.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            spawn_menu_button(parent, "Start", StartButton);
+            spawn_menu_button(parent, "Time Attack", TimeAttackButton);
+
+            // ハウスルール（追加で使える演算子と途中結果の制限）
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                })
+                .with_children(|section| {
+                    section.spawn((
+                        Text::new("House rules"),
+                        TextFont {
+                            font_size: 24.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                    let rows = [
+                        HouseRules::OPTIONAL_OPERATORS
+                            .map(HouseRuleToggle::Operator)
+                            .to_vec(),
+                        vec![
+                            HouseRuleToggle::NonNegativeIntermediates,
+                            HouseRuleToggle::IntegerIntermediates,
+                        ],
+                    ];
+                    for toggles in rows {
+                        section
+                            .spawn(Node {
+                                flex_direction: FlexDirection::Row,
+                                column_gap: Val::Px(10.0),
+                                ..default()
+                            })
+                            .with_children(|row| {
+                                for toggle in toggles {
+                                    spawn_house_rule_toggle(row, toggle, &house_rules);
+                                }
+                            });
+                    }
+                });
+        });
+}
+
+// ハウスルールの表示名
+fn house_rule_label(toggle: HouseRuleToggle) -> String {
+    match toggle {
+        HouseRuleToggle::Operator(op) => format!("Use {}", op.symbol()),
+        HouseRuleToggle::NonNegativeIntermediates => "No negatives".to_string(),
+        HouseRuleToggle::IntegerIntermediates => "Integers only".to_string(),
+    }
+}
+
+// ハウスルールが有効か
+fn house_rule_enabled(toggle: HouseRuleToggle, house_rules: &HouseRules) -> bool {
+    match toggle {
+        HouseRuleToggle::Operator(op) => house_rules.operators.contains(op),
+        HouseRuleToggle::NonNegativeIntermediates => house_rules.non_negative_intermediates,
+        HouseRuleToggle::IntegerIntermediates => house_rules.integer_intermediates,
+    }
+}
+
+// ハウスルールの切り替えボタンを生成（有効なルールは色で示す）
+fn spawn_house_rule_toggle(
+    parent: &mut ChildSpawnerCommands,
+    toggle: HouseRuleToggle,
+    house_rules: &HouseRules,
+) {
+    let color = if house_rule_enabled(toggle, house_rules) {
+        HOUSE_RULE_ON_COLOR
+    } else {
+        HOUSE_RULE_OFF_COLOR
+    };
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(130.0),
+                height: Val::Px(44.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(color),
+            toggle,
+        ))
+        .with_children(|button| {
+            button.spawn((
+                Text::new(house_rule_label(toggle)),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+// メニュー用のボタンを生成
+fn spawn_menu_button(parent: &mut ChildSpawnerCommands, label: &str, marker: impl Component) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(200.0),
+                height: Val::Px(60.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(MENU_BUTTON_COLOR),
+            marker,
+        ))
+        .with_children(|button| {
+            button.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 28.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+// メインメニューシステム - 通常モードかタイムアタックでプレイを始める
+pub fn main_menu_system(
+    mut interaction_query: StartButtonQuery,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut time_attack: ResMut<TimeAttack>,
+) {
+    for (interaction, mut color, time_attack_button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                time_attack.active = time_attack_button.is_some();
+                next_state.set(GameState::Playing);
+
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered => {
+                *color = MENU_BUTTON_HOVERED_COLOR.into();
+            }
+            Interaction::None => {
+                *color = MENU_BUTTON_COLOR.into();
+            }
+        }
+    }
+}
+
+// ハウスルール切り替えシステム - 押したルールの有効・無効を切り替える
+pub fn house_rule_toggle_system(
+    mut interaction_query: HouseRuleToggleQuery,
+    mut house_rules: ResMut<HouseRules>,
+) {
+    for (interaction, mut color, &toggle) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                match toggle {
+                    HouseRuleToggle::Operator(op) => house_rules.toggle_operator(op),
+                    HouseRuleToggle::NonNegativeIntermediates => {
+                        house_rules.non_negative_intermediates =
+                            !house_rules.non_negative_intermediates;
+                    }
+                    HouseRuleToggle::IntegerIntermediates => {
+                        house_rules.integer_intermediates = !house_rules.integer_intermediates;
+                    }
+                }
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered | Interaction::None => {
+                *color = if house_rule_enabled(toggle, &house_rules) {
+                    HOUSE_RULE_ON_COLOR
+                } else {
+                    HOUSE_RULE_OFF_COLOR
+                }
+                .into();
+            }
+        }
+    }
+}
+
+// 新しいゲームの開始システム - 進行状況をリセットしてステージ1から始める
+#[allow(clippy::too_many_arguments)]
+pub fn start_new_game(
+    mut game_progress: ResMut<GameProgress>,
+    mut game_numbers: ResMut<GameNumbers>,
+    mut calc_state: ResMut<CalculationState>,
+    mut rules: ResMut<RuleSet>,
+    mut daily: ResMut<DailyChallenge>,
+    mut rng: ResMut<PuzzleRng>,
+    mut time_attack: ResMut<TimeAttack>,
+    house_rules: Res<HouseRules>,
+) {
+    *game_progress = GameProgress::default();
+    time_attack.restart();
+    *daily = DailyChallenge::default();
+    (*rules, *game_numbers) =
+        generate_stage_board(&house_rules, game_progress.current_stage, &mut rng);
+    calc_state.reset(&game_numbers);
+
+    info!("Starting a new game");
+}
+
+// ハウスルールに従ってステージのルールと盤面を生成
+// （ハウスルールでは解ける盤面が見つからない場合は標準のルールで出題する）
+fn generate_stage_board(
+    house_rules: &HouseRules,
+    stage: u32,
+    rng: &mut PuzzleRng,
+) -> (RuleSet, GameNumbers) {
+    let rules = house_rules.rules_for_stage(stage);
+    if let Some(numbers) = GameNumbers::generate_for_stage(&rules, stage, rng) {
+        return (rules, numbers);
+    }
+    warn!("No solvable board under the house rules, using the standard rules");
+    let rules = RuleSet::default();
+    let numbers = GameNumbers::generate_for_stage(&rules, stage, rng)
+        .expect("the standard rules always have solvable boards");
+    (rules, numbers)
+}
+
+// ゲームオーバー画面の生成システム
+pub fn setup_game_over_screen(mut commands: Commands, game_progress: Res<GameProgress>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+            PopupOverlay,
+            StateScoped(GameState::GameOver),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Game Over"),
+                TextFont {
+                    font_size: 56.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.3, 0.3)),
+            ));
+
+            // 今回のゲームの結果
+            for line in [
+                format!("Stages cleared: {}", game_progress.stages_cleared),
+                format!("Final score: {}", game_progress.score),
+                format!("Best streak: {}", game_progress.best_streak),
+            ] {
+                parent.spawn((
+                    Text::new(line),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            }
+
+            spawn_menu_button(parent, "Retry", RetryButton);
+            spawn_menu_button(parent, "Main Menu", MainMenuButton);
+        });
+}
+
+// ゲームオーバー・タイムアタックの結果画面のシステム - リトライまたはメインメニューに戻る
+pub fn game_over_menu_system(
+    mut interaction_query: GameOverMenuQuery,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color, retry) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                // リトライ時の進行状況のリセットは状態遷移のスケジュールで行う
+                next_state.set(if retry.is_some() {
+                    GameState::Playing
+                } else {
+                    GameState::MainMenu
+                });
+
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered => {
+                *color = MENU_BUTTON_HOVERED_COLOR.into();
+            }
+            Interaction::None => {
+                *color = MENU_BUTTON_COLOR.into();
+            }
+        }
+    }
+}
+
+// タイムアタックの結果画面の生成システム
+pub fn setup_time_up_screen(
+    mut commands: Commands,
+    game_progress: Res<GameProgress>,
+    time_attack: Res<TimeAttack>,
+) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+            PopupOverlay,
+            StateScoped(GameState::TimeUp),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Time Up!"),
+                TextFont {
+                    font_size: 56.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.7, 0.2)),
+            ));
+
+            // タイムアタックの結果
+            for line in [
+                format!("Stages cleared: {}", game_progress.stages_cleared),
+                format!("Final score: {}", game_progress.score),
+                format!("Best streak: {}", game_progress.best_streak),
+                format!("Time bonus: +{}s", time_attack.bonus_total.as_secs()),
+            ] {
+                parent.spawn((
+                    Text::new(line),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            }
+
+            spawn_menu_button(parent, "Retry", RetryButton);
+            spawn_menu_button(parent, "Main Menu", MainMenuButton);
+        });
+}
+
+// タイムアタックの時計システム - プレイ中のみ残り時間を減らし、0になったら結果画面へ
+pub fn time_attack_clock_system(
+    time: Res<Time>,
+    mut time_attack: ResMut<TimeAttack>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if time_attack.tick(time.delta()) {
+        next_state.set(GameState::TimeUp);
+
+        info!("Time up!");
+    }
+}
+
+// 一時停止メニュー生成システム - ゲーム内の時間も止める
+pub fn setup_pause_menu(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    time.pause();
 
-    // メインUIコンテナの作成
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(20.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+            PopupOverlay,
+            StateScoped(GameState::Paused),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Paused"),
+                TextFont {
+                    font_size: 48.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            spawn_menu_button(parent, "Resume", ResumeButton);
+            spawn_menu_button(parent, "Main Menu", MainMenuButton);
+        });
+}
+
+// 一時停止の解除時にゲーム内の時間を再開
+pub fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+// 一時停止の切り替えシステム - Escキーでプレイ中と一時停止を切り替える
+pub fn pause_input_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    input: Res<CodeInputState>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // パズルコード入力中のEscキーは入力の終了に使う
+    if !keyboard.just_pressed(KeyCode::Escape) || input.focused {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+// 一時停止メニューシステム - 再開またはメインメニューに戻る
+pub fn pause_menu_system(
+    mut interaction_query: PauseMenuQuery,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color, resume) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                next_state.set(if resume.is_some() {
+                    GameState::Playing
+                } else {
+                    GameState::MainMenu
+                });
+
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered => {
+                *color = MENU_BUTTON_HOVERED_COLOR.into();
+            }
+            Interaction::None => {
+                *color = MENU_BUTTON_COLOR.into();
+            }
+        }
+    }
+}
+
+// ゲーム画面の生成システム
+pub fn setup_ui(mut commands: Commands, rules: Res<RuleSet>) {
+    // メインUIコンテナの作成（メインメニューに戻ると削除される）
     commands
         .spawn((
             Node {
@@ -46,6 +688,7 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
             },
             BackgroundColor(Color::srgb(0.1, 0.1, 0.1)),
             GameScreenContainer,
+            StateScoped(InGame),
         ))
         .with_children(|parent| {
             // Title and game info
@@ -61,12 +704,13 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
                 .with_children(|title_parent| {
                     // Title
                     title_parent.spawn((
-                        Text::new("Make 10 Game"),
+                        Text::new(format!("{} Game", rules.title())),
                         TextFont {
                             font_size: 48.0,
                             ..default()
                         },
                         TextColor(Color::WHITE),
+                        TitleDisplay,
                     ));
 
                     // Stage and Score info
@@ -99,51 +743,87 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
                                 },
                                 TextColor(Color::srgb(0.8, 0.8, 0.8)),
                             ));
+
+                            // Lives display
+                            info_parent.spawn((
+                                Text::new(format!("Lives: {}", STARTING_LIVES)),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(Color::srgb(0.9, 0.4, 0.4)),
+                                LivesDisplay,
+                            ));
+
+                            // Time attack clock display（通常モードでは空）
+                            info_parent.spawn((
+                                Text::new(""),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(Color::srgb(0.4, 0.8, 0.9)),
+                                TimeAttackDisplay,
+                            ));
+
+                            // Difficulty display
+                            info_parent.spawn((
+                                Text::new("Difficulty: "),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(Color::srgb(0.9, 0.8, 0.3)),
+                                DifficultyDisplay,
+                            ));
+
+                            // Rule display
+                            info_parent.spawn((
+                                Text::new("Rule: "),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(Color::srgb(0.9, 0.6, 0.6)),
+                                RuleDisplay,
+                            ));
+
+                            // Daily status display
+                            info_parent.spawn((
+                                Text::new("Daily: "),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(Color::srgb(0.5, 0.8, 0.5)),
+                                DailyStatusDisplay,
+                            ));
+
+                            // Puzzle code display
+                            info_parent.spawn((
+                                Text::new("Code: "),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(Color::srgb(0.6, 0.7, 0.9)),
+                                PuzzleCodeDisplay,
+                            ));
                         });
                 });
 
-            // Numbers display area
-            parent
-                .spawn((Node {
+            // Numbers display area（数字ボタンはnumber_display_systemで生成）
+            parent.spawn((
+                Node {
                     flex_direction: FlexDirection::Row,
                     justify_content: JustifyContent::Center,
                     align_items: AlignItems::Center,
                     margin: UiRect::bottom(Val::Px(20.0)),
                     column_gap: Val::Px(20.0),
                     ..default()
-                },))
-                .with_children(|numbers_parent| {
-                    // ゲームの数字を表示
-                    for i in 0..4 {
-                        let digit_value = game_numbers.digits[i];
-                        numbers_parent
-                            .spawn((
-                                Button,
-                                Node {
-                                    width: Val::Px(80.0),
-                                    height: Val::Px(80.0),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                },
-                                BackgroundColor(Color::srgb(0.3, 0.5, 0.7)),
-                                NumberDisplay {
-                                    value: digit_value as u32,
-                                    index: i,
-                                },
-                            ))
-                            .with_children(|button_parent| {
-                                button_parent.spawn((
-                                    Text::new(digit_value.to_string()),
-                                    TextFont {
-                                        font_size: 32.0,
-                                        ..default()
-                                    },
-                                    TextColor(Color::WHITE),
-                                ));
-                            });
-                    }
-                });
+                },
+                NumbersContainer,
+            ));
 
             // 演算ボタンエリア
             parent
@@ -156,8 +836,19 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
                     ..default()
                 },))
                 .with_children(|operators_parent| {
-                    let operators = ['+', '-', '*', '/'];
-                    for &op in &operators {
+                    // 演算子ボタン（operator_buttons_systemで生成）
+                    operators_parent.spawn((
+                        Node {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(15.0),
+                            ..default()
+                        },
+                        OperatorsContainer,
+                    ));
+
+                    // 括弧ボタン
+                    for (label, open) in [("(", true), (")", false)] {
                         operators_parent
                             .spawn((
                                 Button,
@@ -168,12 +859,12 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
                                     align_items: AlignItems::Center,
                                     ..default()
                                 },
-                                BackgroundColor(Color::srgb(0.5, 0.3, 0.7)),
-                                OperatorButton { operator: op },
+                                BackgroundColor(Color::srgb(0.4, 0.3, 0.6)),
+                                ParenthesisButton { open },
                             ))
                             .with_children(|button_parent| {
                                 button_parent.spawn((
-                                    Text::new(op.to_string()),
+                                    Text::new(label),
                                     TextFont {
                                         font_size: 24.0,
                                         ..default()
@@ -202,7 +893,7 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
                             font_size: 20.0,
                             ..default()
                         },
-                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        TextColor(EXPRESSION_COLOR),
                         ExpressionDisplay,
                     ));
 
@@ -216,31 +907,259 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
                         TextColor(Color::srgb(0.2, 0.8, 0.2)),
                         ResultDisplay,
                     ));
+
+                    // 解答提出ボタン（Enterキーでも提出できる）
+                    calc_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(120.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(SUBMIT_BUTTON_COLOR),
+                            SubmitButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new("Submit"),
+                                TextFont {
+                                    font_size: 18.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+
+                    // ヒント表示
+                    calc_parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(0.9, 0.9, 0.5)),
+                        HintDisplay,
+                    ));
+                });
+
+            // バックスペース、リセット、モード切り替えボタン
+            parent
+                .spawn((Node {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(15.0),
+                    ..default()
+                },))
+                .with_children(|controls_parent| {
+                    controls_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(120.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.5, 0.4, 0.3)),
+                            BackspaceButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new("Backspace"),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+
+                    controls_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(120.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.6, 0.3, 0.3)),
+                            ResetButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new("Reset"),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+
+                    controls_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(160.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.3, 0.4, 0.4)),
+                            ModeToggleButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new(format!("Mode: {}", PlayMode::default().label())),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+
+                    controls_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(120.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.3, 0.5, 0.3)),
+                            DailyButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new("Daily"),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+
+                    controls_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(120.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.5, 0.5, 0.2)),
+                            HintButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new(format!("Hint (-{})", HINT_COST)),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+
+                    controls_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(120.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.4, 0.25, 0.25)),
+                            GiveUpButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new("Give up"),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
                 });
 
-            // リセットボタン
+            // パズルコード入力エリア
             parent
-                .spawn((
-                    Button,
-                    Node {
-                        width: Val::Px(120.0),
-                        height: Val::Px(40.0),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    BackgroundColor(Color::srgb(0.6, 0.3, 0.3)),
-                    ResetButton,
-                ))
-                .with_children(|button_parent| {
-                    button_parent.spawn((
-                        Text::new("Reset"),
-                        TextFont {
-                            font_size: 16.0,
-                            ..default()
-                        },
-                        TextColor(Color::WHITE),
-                    ));
+                .spawn((Node {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    margin: UiRect::top(Val::Px(15.0)),
+                    column_gap: Val::Px(15.0),
+                    ..default()
+                },))
+                .with_children(|code_parent| {
+                    // 入力欄（クリックで入力開始）
+                    code_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(240.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(CODE_INPUT_COLOR),
+                            CodeInputField,
+                        ))
+                        .with_children(|field_parent| {
+                            field_parent.spawn((
+                                Text::new(CodeInputState::default().display_text()),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                                CodeInputText,
+                            ));
+                        });
+
+                    code_parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(120.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.3, 0.4, 0.6)),
+                            LoadCodeButton,
+                        ))
+                        .with_children(|button_parent| {
+                            button_parent.spawn((
+                                Text::new("Load Code"),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
                 });
         });
 }
@@ -249,66 +1168,121 @@ pub fn setup_ui(mut commands: Commands, game_numbers: Res<GameNumbers>) {
 pub fn button_system(
     mut interaction_query: ButtonQuery,
     mut calc_state: ResMut<CalculationState>,
+    mut play_mode: ResMut<PlayMode>,
     game_numbers: Res<GameNumbers>,
+    rules: Res<RuleSet>,
 ) {
-    for (interaction, mut color, number_display, operator_button, reset_button) in
-        &mut interaction_query
+    for (
+        interaction,
+        mut color,
+        number_display,
+        operator_button,
+        reset_button,
+        mode_button,
+        paren_button,
+        backspace_button,
+    ) in &mut interaction_query
     {
+        // カード合成モードでは選択中、式入力モードでは使用済みのカード
+        let marked = number_display
+            .is_some_and(|number| calc_state.selected_numbers.contains(&number.index));
+        let selected = marked && *play_mode == PlayMode::CardCombine;
+        let used = marked && *play_mode == PlayMode::Expression;
+
         match *interaction {
             Interaction::Pressed => {
-                if let Some(number) = number_display {
+                if let Some(number) = number_display
+                    && *play_mode == PlayMode::CardCombine
+                {
+                    // カード合成モード: カードの選択を切り替える
+                    calc_state.toggle_card(number.index);
+
+                    debug!(
+                        "Card selected: {} (index: {}), selection: {:?}",
+                        number.value, number.index, calc_state.selected_numbers
+                    );
+                } else if let Some(number) = number_display {
                     // 数字ボタンが押された時の処理
                     let digit_value = game_numbers.digits[number.index];
 
-                    // 新しい数字を式に追加
-                    if calc_state.expression.is_empty() {
-                        calc_state.expression = digit_value.to_string();
-                    } else {
-                        // 最後の文字が演算子の場合のみ数字を追加
-                        if let Some(last_char) = calc_state.expression.chars().last() {
-                            if "+-*/".contains(last_char) {
-                                calc_state.expression.push_str(&format!(" {}", digit_value));
-
-                                // 計算を実行（4項演算まで対応）
-                                if let Some(result) = evaluate_expression(&calc_state.expression) {
-                                    calc_state.result = Some(result);
-                                }
-                            } else {
-                                println!(
-                                    "Cannot add number after another number or without an operator."
-                                );
-                            }
-                        }
+                    // 未使用のカードを、式の先頭、演算子や開き括弧の直後の場合のみ追加
+                    if used {
+                        debug!("This card has already been used.");
+                    } else if !calc_state.push_token(Token::Literal {
+                        index: number.index,
+                        value: digit_value,
+                    }) {
+                        println!(
+                            "Cannot add number after another number or a closing parenthesis."
+                        );
                     }
 
                     println!(
                         "Number button pressed: {} (index: {})",
                         number.value, number.index
                     );
+                } else if let Some(operator) = operator_button
+                    && !operator.enabled
+                {
+                    // ルールで禁止されている、または使用済みの演算子
+                    debug!("Operator {} is not allowed by the rule.", operator.operator);
+                } else if let Some(operator) = operator_button
+                    && *play_mode == PlayMode::CardCombine
+                {
+                    // カード合成モード: 選択中の2枚（単項演算子は1枚）を1枚にまとめる
+                    if let Err(error) = calc_state.combine_selected(operator.operator, &rules) {
+                        debug!("Cannot combine cards: {}", error);
+                    }
+
+                    debug!("Operator button pressed: {}", operator.operator);
                 } else if let Some(operator) = operator_button {
                     // 演算子ボタンが押された時の処理
-                    if !calc_state.expression.is_empty() {
-                        // 最後の文字が数字の場合のみ演算子を追加
-                        if let Some(last_char) = calc_state.expression.chars().last() {
-                            if last_char.is_ascii_digit() {
-                                calc_state
-                                    .expression
-                                    .push_str(&format!(" {}", operator.operator));
-                            }
-                        } else {
-                            println!("Cannot add operator without a preceding number.");
-                        }
+                    // 最後のトークンが数字か閉じ括弧の場合のみ演算子を追加（平方根は数字の前）
+                    if !calc_state.push_token(Token::Operator(operator.operator)) {
+                        println!("Cannot add operator without a preceding number.");
                     }
 
                     println!("Operator button pressed: {}", operator.operator);
                 } else if reset_button.is_some() {
                     // リセットボタンが押された時の処理
-                    calc_state.expression.clear();
-                    calc_state.result = None;
-                    calc_state.selected_numbers.clear();
-                    calc_state.operators.clear();
+                    calc_state.reset(&game_numbers);
 
                     println!("Reset button pressed");
+                } else if let Some(paren) = paren_button {
+                    // 括弧ボタンが押された時の処理（カード合成モードでは不要）
+                    if *play_mode == PlayMode::Expression {
+                        let token = if paren.open {
+                            Token::LeftParen
+                        } else {
+                            Token::RightParen
+                        };
+                        if !calc_state.push_token(token) {
+                            debug!("Cannot add parenthesis here.");
+                        }
+                    }
+
+                    debug!(
+                        "Parenthesis button pressed: {}",
+                        if paren.open { "(" } else { ")" }
+                    );
+                } else if backspace_button.is_some() {
+                    // バックスペースボタンが押された時の処理
+                    match *play_mode {
+                        PlayMode::Expression => {
+                            calc_state.pop_token();
+                        }
+                        PlayMode::CardCombine => {
+                            calc_state.undo_combine();
+                        }
+                    }
+
+                    debug!("Backspace button pressed");
+                } else if mode_button.is_some() {
+                    // モード切り替えボタンが押された時の処理
+                    *play_mode = play_mode.toggled();
+                    calc_state.reset(&game_numbers);
+
+                    debug!("Play mode: {}", play_mode.label());
                 }
 
                 // 押下時の色変更
@@ -316,22 +1290,50 @@ pub fn button_system(
             }
             Interaction::Hovered => {
                 // ホバー時の色変更
-                if number_display.is_some() {
+                if used {
+                    *color = USED_CARD_COLOR.into();
+                } else if selected {
+                    *color = Color::srgb(0.9, 0.7, 0.3).into();
+                } else if number_display.is_some() {
                     *color = Color::srgb(0.4, 0.6, 0.8).into();
+                } else if let Some(operator) = operator_button
+                    && !operator.enabled
+                {
+                    *color = DISABLED_OPERATOR_COLOR.into();
                 } else if operator_button.is_some() {
                     *color = Color::srgb(0.6, 0.4, 0.8).into();
+                } else if paren_button.is_some() {
+                    *color = Color::srgb(0.5, 0.4, 0.7).into();
+                } else if backspace_button.is_some() {
+                    *color = Color::srgb(0.6, 0.5, 0.4).into();
                 } else if reset_button.is_some() {
                     *color = Color::srgb(0.7, 0.4, 0.4).into();
+                } else if mode_button.is_some() {
+                    *color = Color::srgb(0.4, 0.5, 0.5).into();
                 }
             }
             Interaction::None => {
                 // 通常時の色に戻す
-                if number_display.is_some() {
-                    *color = Color::srgb(0.3, 0.5, 0.7).into();
+                if used {
+                    *color = USED_CARD_COLOR.into();
+                } else if selected {
+                    *color = SELECTED_CARD_COLOR.into();
+                } else if number_display.is_some() {
+                    *color = NUMBER_BUTTON_COLOR.into();
+                } else if let Some(operator) = operator_button
+                    && !operator.enabled
+                {
+                    *color = DISABLED_OPERATOR_COLOR.into();
                 } else if operator_button.is_some() {
-                    *color = Color::srgb(0.5, 0.3, 0.7).into();
+                    *color = OPERATOR_BUTTON_COLOR.into();
+                } else if paren_button.is_some() {
+                    *color = Color::srgb(0.4, 0.3, 0.6).into();
+                } else if backspace_button.is_some() {
+                    *color = Color::srgb(0.5, 0.4, 0.3).into();
                 } else if reset_button.is_some() {
                     *color = Color::srgb(0.6, 0.3, 0.3).into();
+                } else if mode_button.is_some() {
+                    *color = Color::srgb(0.3, 0.4, 0.4).into();
                 }
             }
         }
@@ -341,19 +1343,153 @@ pub fn button_system(
 // 数字表示システム - ゲーム状態と連携
 pub fn number_display_system(
     game_numbers: Res<GameNumbers>,
-    mut number_query: Query<(&NumberDisplay, &Children)>,
-    mut text_query: Query<&mut Text>,
+    calc_state: Res<CalculationState>,
+    play_mode: Res<PlayMode>,
+    mut commands: Commands,
+    container_query: Query<Entity, With<NumbersContainer>>,
+) {
+    // 表示する数字、使用済み・選択中のカード、盤面が変わった場合のみボタンを作り直す
+    if game_numbers.is_changed() || play_mode.is_changed() || calc_state.is_changed() {
+        for container in container_query.iter() {
+            commands
+                .entity(container)
+                .despawn_related::<Children>()
+                .with_children(|numbers_parent| match *play_mode {
+                    PlayMode::Expression => {
+                        for (i, &digit_value) in game_numbers.digits.iter().enumerate() {
+                            let color = if calc_state.selected_numbers.contains(&i) {
+                                USED_CARD_COLOR
+                            } else {
+                                NUMBER_BUTTON_COLOR
+                            };
+                            spawn_number_button(
+                                numbers_parent,
+                                i,
+                                Rational::from(digit_value),
+                                color,
+                            );
+                        }
+                    }
+                    PlayMode::CardCombine => {
+                        for (i, card) in calc_state.board.cards.iter().enumerate() {
+                            let color = if calc_state.selected_numbers.contains(&i) {
+                                SELECTED_CARD_COLOR
+                            } else {
+                                NUMBER_BUTTON_COLOR
+                            };
+                            spawn_number_button(numbers_parent, i, card.value, color);
+                        }
+                    }
+                });
+        }
+    }
+}
+
+// ルールで有効な演算子のボタンを生成するシステム
+//
+// 特別ルールで禁止された演算子や、1回ずつ使うルールで使用済みの演算子は無効な色で表示する。
+pub fn operator_buttons_system(
+    rules: Res<RuleSet>,
+    calc_state: Res<CalculationState>,
+    play_mode: Res<PlayMode>,
+    mut commands: Commands,
+    container_query: Query<Entity, With<OperatorsContainer>>,
+) {
+    // ルールや入力中の式が変わった場合のみボタンを作り直す
+    if rules.is_changed() || calc_state.is_changed() || play_mode.is_changed() {
+        let used = calc_state.used_operators(*play_mode);
+        for container in container_query.iter() {
+            commands
+                .entity(container)
+                .despawn_related::<Children>()
+                .with_children(|operators_parent| {
+                    for op in rules.operators.iter() {
+                        let enabled = rules.can_use_operator(op, &used);
+                        spawn_operator_button(operators_parent, op, enabled);
+                    }
+                });
+        }
+    }
+}
+
+// 演算子ボタンを生成
+fn spawn_operator_button(parent: &mut ChildSpawnerCommands, op: Operator, enabled: bool) {
+    let color = if enabled {
+        OPERATOR_BUTTON_COLOR
+    } else {
+        DISABLED_OPERATOR_COLOR
+    };
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(60.0),
+                height: Val::Px(60.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(color),
+            OperatorButton {
+                operator: op,
+                enabled,
+            },
+        ))
+        .with_children(|button_parent| {
+            button_parent.spawn((
+                Text::new(op.symbol()),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+// 数字ボタンを生成
+fn spawn_number_button(
+    parent: &mut ChildSpawnerCommands,
+    index: usize,
+    value: Rational,
+    color: Color,
 ) {
-    // GameNumbersリソースが変更された場合のみ更新
-    if game_numbers.is_changed() {
-        for (number_display, children) in number_query.iter_mut() {
-            // 対応する桁の値を取得
-            let value = game_numbers.digits[number_display.index];
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(80.0),
+                height: Val::Px(80.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(color),
+            NumberDisplay { value, index },
+        ))
+        .with_children(|button_parent| {
+            button_parent.spawn((
+                Text::new(value.to_string()),
+                TextFont {
+                    font_size: 32.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
 
-            // 子要素のテキストを更新
+// プレイモード表示システム - モード切り替えボタンのラベルを更新
+pub fn play_mode_display_system(
+    play_mode: Res<PlayMode>,
+    button_query: Query<&Children, With<ModeToggleButton>>,
+    mut text_query: Query<&mut Text>,
+) {
+    if play_mode.is_changed() {
+        for children in button_query.iter() {
             for child in children.iter() {
                 if let Ok(mut text) = text_query.get_mut(child) {
-                    **text = value.to_string();
+                    **text = format!("Mode: {}", play_mode.label());
                 }
             }
         }
@@ -363,27 +1499,38 @@ pub fn number_display_system(
 // 計算表示システム - 計算式と結果の更新
 pub fn calculation_display_system(
     calc_state: Res<CalculationState>,
+    play_mode: Res<PlayMode>,
+    rules: Res<RuleSet>,
     mut expr_query: Query<&mut Text, (With<ExpressionDisplay>, Without<ResultDisplay>)>,
     mut result_query: Query<&mut Text, (With<ResultDisplay>, Without<ExpressionDisplay>)>,
 ) {
     // CalculationStateが変更された場合のみ更新
-    if calc_state.is_changed() {
+    if calc_state.is_changed() || play_mode.is_changed() || rules.is_changed() {
         // 計算式表示の更新
         if let Ok(mut expr_text) = expr_query.single_mut() {
-            if calc_state.expression.is_empty() {
-                **expr_text = "Expression: ".to_string();
-            } else {
-                **expr_text = format!("Expression: {}", calc_state.expression);
-            }
+            **expr_text = format!("Expression: {}", calc_state.expression_text(*play_mode));
         }
 
         // 計算結果表示の更新
         if let Ok(mut result_text) = result_query.single_mut() {
-            match calc_state.result {
-                Some(result) => {
+            // 提出した解答が間違っていた場合はその理由、
+            // ルールに違反している場合は計算結果と理由を表示
+            let violation = calc_state
+                .expr
+                .as_ref()
+                .and_then(|expr| rules.check(expr).err());
+            if let Some(failure) = &calc_state.failure {
+                **result_text = format!("Wrong: {}", failure);
+                return;
+            }
+            match (calc_state.result, violation) {
+                (Some(result), Some(violation)) => {
+                    **result_text = format!("Result: {} ({})", result, violation);
+                }
+                (Some(result), None) => {
                     **result_text = format!("Result: {}", result);
                 }
-                None => {
+                (None, _) => {
                     **result_text = "Result: ".to_string();
                 }
             }
@@ -391,164 +1538,350 @@ pub fn calculation_display_system(
     }
 }
 
-// 計算式評価関数（4項演算まで対応、演算子優先度考慮）
-pub fn evaluate_expression(expression: &str) -> Option<f64> {
-    let parts: Vec<&str> = expression.split_whitespace().collect();
+// 解答提出システム - 提出ボタンかEnterキーで式を判定し、正解ならステージクリア
+#[allow(clippy::too_many_arguments)]
+pub fn submit_system(
+    mut interaction_query: SubmitButtonQuery,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    code_input: Res<CodeInputState>,
+    mut calc_state: ResMut<CalculationState>,
+    play_mode: Res<PlayMode>,
+    game_numbers: Res<GameNumbers>,
+    rules: Res<RuleSet>,
+    analysis: Res<BoardAnalysis>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut game_progress: ResMut<GameProgress>,
+    mut time_attack: ResMut<TimeAttack>,
+    mut commands: Commands,
+    expr_query: Query<Entity, With<ExpressionDisplay>>,
+) {
+    // パズルコード入力中のEnterキーはコードの読み込みに使う
+    let mut submit =
+        !code_input.focused && keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]);
+
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                submit = true;
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered => {
+                *color = Color::srgb(0.3, 0.6, 0.4).into();
+            }
+            Interaction::None => {
+                *color = SUBMIT_BUTTON_COLOR.into();
+            }
+        }
+    }
+
+    if !submit {
+        return;
+    }
+
+    match calc_state.submit(*play_mode, &game_numbers, &rules) {
+        Ok(()) => {
+            // すべてのカードを1回ずつ使い、ルールを守って目標の数を作った場合、ステージクリア
+            next_state.set(GameState::StageClear);
+            game_progress.clear_stage();
+
+            // タイムアタック中は盤面の難易度に応じて残り時間を増やす
+            if time_attack.active {
+                let bonus = time_attack.add_clear_bonus(analysis.difficulty());
+                info!("Time bonus: +{}s", bonus.as_secs());
+            }
+
+            println!("Stage Clear! Result: {}", rules.target);
+        }
+        Err(failure) => {
+            // 計算式表示を揺らして赤く光らせる
+            for entity in expr_query.iter() {
+                commands.entity(entity).insert(Shake {
+                    timer: Timer::from_seconds(SHAKE_DURATION, TimerMode::Once),
+                });
+            }
+
+            // 完成した式が間違っていた場合のみライフを失う
+            if failure.is_wrong_answer() && game_progress.lose_life() {
+                next_state.set(GameState::GameOver);
+            }
+
+            info!(
+                "Wrong answer: {} ({} lives left)",
+                failure, game_progress.lives
+            );
+        }
+    }
+}
+
+// 間違った解答の演出システム - 計算式表示を揺らし、赤から元の色に戻す
+pub fn shake_feedback_system(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Shake, &mut Node, &mut TextColor)>,
+) {
+    for (entity, mut shake, mut node, mut color) in &mut query {
+        shake.timer.tick(time.delta());
+        if shake.timer.finished() {
+            node.left = Val::Px(0.0);
+            color.0 = EXPRESSION_COLOR;
+            commands.entity(entity).remove::<Shake>();
+        } else {
+            let progress = shake.timer.fraction();
+            let offset = (shake.timer.elapsed_secs() * SHAKE_FREQUENCY).sin() * SHAKE_AMPLITUDE;
+            node.left = Val::Px(offset * (1.0 - progress));
+            color.0 = FAILURE_COLOR.mix(&EXPRESSION_COLOR, progress);
+        }
+    }
+}
+
+// ポップアップシステム
+#[allow(clippy::too_many_arguments)]
+pub fn popup_system(
+    mut interaction_query: PopupInteractionQuery,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut game_progress: ResMut<GameProgress>,
+    mut calc_state: ResMut<CalculationState>,
+    mut game_numbers: ResMut<GameNumbers>,
+    mut rng: ResMut<PuzzleRng>,
+    mut daily: ResMut<DailyChallenge>,
+    mut daily_records: ResMut<DailyRecords>,
+    time: Res<Time>,
+    mut rules: ResMut<RuleSet>,
+    house_rules: Res<HouseRules>,
+) {
+    for (interaction, mut color, next_button) in &mut interaction_query {
+        if let Interaction::Pressed = *interaction
+            && next_button.is_some()
+        {
+            // 次のステージに進む（ギブアップした場合も新しい盤面で続ける）
+            // ポップアップは状態を抜けると自動で削除される
+            game_progress.current_stage += 1;
+            next_state.set(GameState::Playing);
+
+            // デイリーチャレンジ中は日付から決まる次の盤面、
+            // それ以外はステージに応じた特別ルールと難易度で新しい数字を生成
+            let finished = daily.advance(time.elapsed_secs_f64());
+            if let Some(date) = daily.date {
+                *game_numbers = GameNumbers::for_date(date.year, date.month, date.day, daily.index);
+            } else {
+                if let Some((date, elapsed)) = finished {
+                    daily_records.record(date, elapsed);
+                    if let Err(error) = daily_records.save(DAILY_RECORDS_PATH) {
+                        error!("Failed to save daily record: {}", error);
+                    }
+                    info!("Daily {} completed in {:.1}s", date, elapsed);
+                }
+                // パズルコードで読み込んだルールはその盤面限りとし、ハウスルールから作り直す
+                (*rules, *game_numbers) =
+                    generate_stage_board(&house_rules, game_progress.current_stage, &mut rng);
+            }
+            calc_state.reset(&game_numbers);
+
+            println!("Starting Stage {}", game_progress.current_stage);
+
+            *color = Color::srgb(0.8, 0.8, 0.8).into();
+        }
+    }
+}
+
+// ステージクリアポップアップを生成（ステージクリア状態を抜けると削除される）
+pub fn spawn_stage_clear_popup(
+    mut commands: Commands,
+    game_progress: Res<GameProgress>,
+    daily: Res<DailyChallenge>,
+    game_numbers: Res<GameNumbers>,
+    rules: Res<RuleSet>,
+    calc_state: Res<CalculationState>,
+) {
+    let solution = calc_state.expr.as_ref();
+    let heading = match daily.date {
+        Some(date) => format!(
+            "Daily {} ({}/{}) Completed",
+            date,
+            daily.index + 1,
+            DAILY_PUZZLE_COUNT
+        ),
+        None => format!("Stage {} Completed", game_progress.current_stage),
+    };
+
+    // オーバーレイ（背景）
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+            PopupOverlay,
+            StateScoped(GameState::StageClear),
+        ))
+        .with_children(|overlay| {
+            // ポップアップ本体
+            overlay
+                .spawn((
+                    Node {
+                        width: Val::Px(400.0),
+                        height: Val::Px(320.0),
+                        flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(20.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.3, 0.4)),
+                    StageClearPopup,
+                ))
+                .with_children(|popup| {
+                    // タイトル
+                    popup.spawn((
+                        Text::new("Stage Clear!"),
+                        TextFont {
+                            font_size: 36.0,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(0.2, 0.8, 0.2)),
+                    ));
+
+                    // ステージ情報
+                    popup.spawn((
+                        Text::new(heading),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+
+                    // プレイヤーの解答
+                    if let Some(solution) = solution {
+                        popup.spawn((
+                            Text::new(format!("{} = {}", solution, rules.target)),
+                            TextFont {
+                                font_size: 22.0,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.6)),
+                        ));
+                    }
 
-    // 最低3つの部分が必要（数字 演算子 数字）
-    if parts.len() < 3 {
-        println!("Invalid expression: length is less than 3, {}", parts.len());
-        return None;
-    }
+                    // 盤面のパズルコード
+                    if let Ok(code) = game_numbers.to_code(&rules) {
+                        popup.spawn((
+                            Text::new(format!("Code: {}", code)),
+                            TextFont {
+                                font_size: 16.0,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.6, 0.7, 0.9)),
+                        ));
+                    }
 
-    // 奇数個の部分が必要（数字で始まり数字で終わる）
-    if parts.len() % 2 == 0 {
-        println!("Invalid expression: length is even, {}", parts.len());
-        return None;
-    }
+                    // スコア情報
+                    popup.spawn((
+                        Text::new(format!("Score: {}", game_progress.score)),
+                        TextFont {
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
 
-    // Make10ゲーム用の入力検証
-    for (i, part) in parts.iter().enumerate() {
-        if i % 2 == 0 {
-            // 数字の位置
-            let num = part.parse::<f64>().ok()?;
-            // 1桁の数字のみ許可（1-9）
-            if !(1.0..=9.0).contains(&num) || num.fract() != 0.0 {
-                println!("Invalid number: {}", part);
-                return None;
-            }
-        } else {
-            // 演算子の位置
-            if !matches!(*part, "+" | "-" | "*" | "/") {
-                println!("Invalid operator: {}", part);
-                return None;
-            }
-        }
-    }
+                    // 次へボタン
+                    popup
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(150.0),
+                                height: Val::Px(50.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.2, 0.6, 0.2)),
+                            NextStageButton,
+                        ))
+                        .with_children(|button| {
+                            button.spawn((
+                                Text::new("Next Stage"),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+                });
+        });
+}
 
-    // 数字と演算子のベクターに分割
-    let mut numbers = Vec::new();
-    let mut operators = Vec::new();
+// ギブアップボタンシステム - ペナルティを与えてすべての解を表示
+pub fn give_up_button_system(
+    mut interaction_query: GiveUpButtonQuery,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut game_progress: ResMut<GameProgress>,
+    daily: Res<DailyChallenge>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                // デイリーチャレンジの盤面は飛ばせない
+                if daily.is_active() {
+                    debug!("Daily puzzles cannot be skipped.");
+                } else {
+                    // ライフがなくなった場合は解答を表示せずにゲームオーバー
+                    let game_over = game_progress.give_up();
+                    next_state.set(if game_over {
+                        GameState::GameOver
+                    } else {
+                        GameState::GaveUp
+                    });
 
-    for (i, part) in parts.iter().enumerate() {
-        if i % 2 == 0 {
-            numbers.push(part.parse::<f64>().ok()?);
-        } else {
-            operators.push(*part);
-        }
-    }
+                    info!(
+                        "Gave up on stage {} ({} lives left)",
+                        game_progress.current_stage, game_progress.lives
+                    );
+                }
 
-    // 演算子優先度を考慮した計算
-    // 最初に掛け算と割り算を処理
-    let mut i = 0;
-    while i < operators.len() {
-        match operators[i] {
-            "*" => {
-                let result = numbers[i] * numbers[i + 1];
-                numbers[i] = result;
-                numbers.remove(i + 1);
-                operators.remove(i);
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
             }
-            "/" => {
-                if numbers[i + 1] == 0.0 {
-                    println!("Division by zero in expression: {}", expression);
-                    return None;
-                }
-                let result = numbers[i] / numbers[i + 1];
-                numbers[i] = result;
-                numbers.remove(i + 1);
-                operators.remove(i);
+            Interaction::Hovered => {
+                *color = Color::srgb(0.5, 0.35, 0.35).into();
             }
-            _ => {
-                i += 1;
+            Interaction::None => {
+                *color = Color::srgb(0.4, 0.25, 0.25).into();
             }
         }
     }
-
-    // 次に足し算と引き算を左から右へ処理
-    let mut result = numbers[0];
-    for (i, &operator) in operators.iter().enumerate() {
-        match operator {
-            "+" => result += numbers[i + 1],
-            "-" => result -= numbers[i + 1],
-            _ => return None, // この時点で*と/は既に処理済みなのでエラー
-        }
-    }
-
-    Some(result)
 }
 
-// ステージクリア検出システム
-pub fn stage_clear_detection_system(
-    calc_state: Res<CalculationState>,
-    mut game_state: ResMut<GameState>,
-    mut game_progress: ResMut<GameProgress>,
-    mut commands: Commands,
-    popup_query: Query<Entity, With<StageClearPopup>>,
-) {
-    // 計算結果が10の場合、ステージクリア
-    if let Some(result) = calc_state.result {
-        if (result - 10.0).abs() < f64::EPSILON && *game_state == GameState::Playing {
-            *game_state = GameState::StageClear;
-            game_progress.stages_cleared += 1;
-            game_progress.score += 100; // ステージクリアごとに100ポイント
-
-            // ポップアップが存在しない場合のみ作成
-            if popup_query.is_empty() {
-                spawn_stage_clear_popup(&mut commands, &game_progress);
-            }
-
-            println!("Stage Clear! Result: {}", result);
-        }
+// ギブアップ時の解の一覧の見出し
+fn reveal_heading(count: usize) -> String {
+    match count {
+        0 => "This board has no solution".to_string(),
+        1 => "The only solution:".to_string(),
+        n => format!("All {} solutions:", n),
     }
 }
 
-// ポップアップシステム
-#[allow(clippy::too_many_arguments)]
-pub fn popup_system(
-    mut interaction_query: PopupInteractionQuery,
-    mut game_state: ResMut<GameState>,
-    mut game_progress: ResMut<GameProgress>,
-    mut calc_state: ResMut<CalculationState>,
-    mut game_numbers: ResMut<GameNumbers>,
+// ギブアップ時に解を表示するポップアップを生成
+// （ギブアップ状態を抜けると削除される）
+pub fn spawn_reveal_popup(
     mut commands: Commands,
-    popup_query: Query<Entity, With<StageClearPopup>>,
-    overlay_query: Query<Entity, With<PopupOverlay>>,
+    game_progress: Res<GameProgress>,
+    game_numbers: Res<GameNumbers>,
+    rules: Res<RuleSet>,
+    analysis: Res<BoardAnalysis>,
 ) {
-    for (interaction, mut color, next_button) in &mut interaction_query {
-        if let Interaction::Pressed = *interaction {
-            if next_button.is_some() && *game_state == GameState::StageClear {
-                // 次のステージに進む
-                game_progress.current_stage += 1;
-                *game_state = GameState::Playing;
-
-                // 計算状態をリセット
-                calc_state.expression.clear();
-                calc_state.result = None;
-                calc_state.selected_numbers.clear();
-                calc_state.operators.clear();
-
-                // 新しい数字を生成
-                *game_numbers = GameNumbers::new();
-
-                // ポップアップを削除
-                for entity in popup_query.iter() {
-                    commands.entity(entity).despawn();
-                }
-                for entity in overlay_query.iter() {
-                    commands.entity(entity).despawn();
-                }
-
-                println!("Starting Stage {}", game_progress.current_stage);
-
-                *color = Color::srgb(0.8, 0.8, 0.8).into();
-            }
-        }
-    }
-}
+    let solutions = analysis.solutions();
+    info!("Revealing {} solutions", solutions.len());
+    let heading = reveal_heading(solutions.len());
 
-// ステージクリアポップアップを生成
-fn spawn_stage_clear_popup(commands: &mut Commands, game_progress: &GameProgress) {
     // オーバーレイ（背景）
     commands
         .spawn((
@@ -564,37 +1897,48 @@ fn spawn_stage_clear_popup(commands: &mut Commands, game_progress: &GameProgress
             },
             BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
             PopupOverlay,
+            StateScoped(GameState::GaveUp),
         ))
         .with_children(|overlay| {
             // ポップアップ本体
             overlay
                 .spawn((
                     Node {
-                        width: Val::Px(400.0),
-                        height: Val::Px(250.0),
+                        width: Val::Px(640.0),
+                        max_height: Val::Percent(90.0),
                         flex_direction: FlexDirection::Column,
                         justify_content: JustifyContent::Center,
                         align_items: AlignItems::Center,
-                        row_gap: Val::Px(20.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        row_gap: Val::Px(16.0),
+                        overflow: Overflow::clip_y(),
                         ..default()
                     },
-                    BackgroundColor(Color::srgb(0.2, 0.3, 0.4)),
-                    StageClearPopup,
+                    BackgroundColor(Color::srgb(0.35, 0.25, 0.3)),
+                    RevealPopup,
                 ))
                 .with_children(|popup| {
                     // タイトル
                     popup.spawn((
-                        Text::new("Stage Clear!"),
+                        Text::new(format!("Stage {} Given Up", game_progress.current_stage)),
                         TextFont {
-                            font_size: 36.0,
+                            font_size: 32.0,
                             ..default()
                         },
-                        TextColor(Color::srgb(0.2, 0.8, 0.2)),
+                        TextColor(Color::srgb(0.9, 0.5, 0.4)),
                     ));
 
-                    // ステージ情報
                     popup.spawn((
-                        Text::new(format!("Stage {} Completed", game_progress.current_stage)),
+                        Text::new(format!(
+                            "{}  (Make {})",
+                            game_numbers
+                                .digits
+                                .iter()
+                                .map(|d| d.to_string())
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                            rules.target
+                        )),
                         TextFont {
                             font_size: 20.0,
                             ..default()
@@ -602,9 +1946,52 @@ fn spawn_stage_clear_popup(commands: &mut Commands, game_progress: &GameProgress
                         TextColor(Color::WHITE),
                     ));
 
+                    popup.spawn((
+                        Text::new(heading),
+                        TextFont {
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+
+                    // 解の一覧（本質的に異なる解ごとに1つ、括弧は最小限）
+                    // 解の多い盤面ではマウスホイールでスクロールする
+                    popup
+                        .spawn((
+                            Node {
+                                width: Val::Percent(100.0),
+                                max_height: Val::Px(REVEAL_LIST_HEIGHT),
+                                flex_direction: FlexDirection::Row,
+                                flex_wrap: FlexWrap::Wrap,
+                                justify_content: JustifyContent::Center,
+                                column_gap: Val::Px(24.0),
+                                row_gap: Val::Px(6.0),
+                                overflow: Overflow::scroll_y(),
+                                ..default()
+                            },
+                            ScrollPosition::default(),
+                            RevealSolutionList,
+                        ))
+                        .with_children(|list| {
+                            for solution in solutions {
+                                list.spawn((
+                                    Text::new(solution.to_string()),
+                                    TextFont {
+                                        font_size: 16.0,
+                                        ..default()
+                                    },
+                                    TextColor(Color::srgb(0.9, 0.9, 0.6)),
+                                ));
+                            }
+                        });
+
                     // スコア情報
                     popup.spawn((
-                        Text::new(format!("Score: {}", game_progress.score)),
+                        Text::new(format!(
+                            "Penalty: -{}  Score: {}",
+                            GIVE_UP_PENALTY, game_progress.score
+                        )),
                         TextFont {
                             font_size: 18.0,
                             ..default()
@@ -628,7 +2015,7 @@ fn spawn_stage_clear_popup(commands: &mut Commands, game_progress: &GameProgress
                         ))
                         .with_children(|button| {
                             button.spawn((
-                                Text::new("Next Stage"),
+                                Text::new("Continue"),
                                 TextFont {
                                     font_size: 20.0,
                                     ..default()
@@ -640,6 +2027,25 @@ fn spawn_stage_clear_popup(commands: &mut Commands, game_progress: &GameProgress
         });
 }
 
+// ギブアップ時の解の一覧のスクロールシステム - マウスホイールで一覧を上下に動かす
+pub fn reveal_scroll_system(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut list_query: Query<(&mut ScrollPosition, &ComputedNode), With<RevealSolutionList>>,
+) {
+    for event in wheel_events.read() {
+        let delta = match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        };
+        for (mut scroll, node) in &mut list_query {
+            // 一覧の高さを超えてはみ出した分だけスクロールできる
+            let max_offset =
+                (node.content_size().y - node.size().y).max(0.0) * node.inverse_scale_factor();
+            scroll.offset_y = (scroll.offset_y - delta).clamp(0.0, max_offset);
+        }
+    }
+}
+
 // ゲーム情報表示システム（ステージとスコア表示の更新）
 pub fn game_info_display_system(
     game_progress: Res<GameProgress>,
@@ -660,3 +2066,318 @@ pub fn game_info_display_system(
         }
     }
 }
+
+// 残りライフ表示システム
+pub fn lives_display_system(
+    game_progress: Res<GameProgress>,
+    mut query: Query<&mut Text, With<LivesDisplay>>,
+) {
+    if game_progress.is_changed() {
+        for mut text in query.iter_mut() {
+            **text = format!("Lives: {}", game_progress.lives);
+        }
+    }
+}
+
+// タイムアタックの残り時間表示システム
+pub fn time_attack_display_system(
+    time_attack: Res<TimeAttack>,
+    mut query: Query<&mut Text, With<TimeAttackDisplay>>,
+) {
+    if time_attack.is_changed() {
+        for mut text in query.iter_mut() {
+            **text = if time_attack.active {
+                format!("Time: {}", time_attack.clock_text())
+            } else {
+                String::new()
+            };
+        }
+    }
+}
+
+// 盤面解析システム - 盤面やルールが変わったときに1度だけ難易度などを求める
+pub fn board_analysis_system(
+    game_numbers: Res<GameNumbers>,
+    rules: Res<RuleSet>,
+    mut analysis: ResMut<BoardAnalysis>,
+) {
+    if (game_numbers.is_changed() || rules.is_changed()) && !analysis.is_for(&game_numbers, &rules)
+    {
+        *analysis = BoardAnalysis::new(&game_numbers, &rules);
+    }
+}
+
+// 難易度表示システム - 盤面の解析結果が変わったときに星の数を更新
+pub fn difficulty_display_system(
+    analysis: Res<BoardAnalysis>,
+    mut difficulty_query: Query<&mut Text, With<DifficultyDisplay>>,
+) {
+    if analysis.is_changed() {
+        let stars = analysis
+            .difficulty()
+            .map(|difficulty| difficulty.star_text())
+            .unwrap_or_default();
+
+        for mut text in difficulty_query.iter_mut() {
+            **text = format!("Difficulty: {}", stars);
+        }
+    }
+}
+
+// ルール表示システム - ルールが変わったときに説明を更新
+pub fn rule_display_system(
+    rules: Res<RuleSet>,
+    mut rule_query: Query<&mut Text, With<RuleDisplay>>,
+) {
+    if rules.is_changed() {
+        for mut text in rule_query.iter_mut() {
+            **text = format!("Rule: {}", rules.description());
+        }
+    }
+}
+
+// デイリーボタンシステム - 今日のデイリーチャレンジを開始
+#[allow(clippy::too_many_arguments)]
+pub fn daily_button_system(
+    mut interaction_query: DailyButtonQuery,
+    mut daily: ResMut<DailyChallenge>,
+    mut game_numbers: ResMut<GameNumbers>,
+    mut calc_state: ResMut<CalculationState>,
+    mut rules: ResMut<RuleSet>,
+    mut game_progress: ResMut<GameProgress>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                // デイリーチャレンジ中でない場合のみ開始
+                if !daily.is_active() {
+                    let today = DailyDate::today();
+                    daily.start(today, time.elapsed_secs_f64());
+                    // デイリーパズルは標準のルールで出題される
+                    *rules = RuleSet::default();
+                    *game_numbers = GameNumbers::for_date(today.year, today.month, today.day, 0);
+                    calc_state.reset(&game_numbers);
+                    // 解きかけの盤面はギブアップ扱い（ライフがなくなった場合はゲームオーバー）
+                    if game_progress.replace_board() {
+                        next_state.set(GameState::GameOver);
+                    }
+
+                    info!("Daily {} started", today);
+                }
+
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered => {
+                *color = Color::srgb(0.4, 0.6, 0.4).into();
+            }
+            Interaction::None => {
+                *color = Color::srgb(0.3, 0.5, 0.3).into();
+            }
+        }
+    }
+}
+
+// ヒントボタンシステム - スコアを消費して次の段階のヒントを表示
+pub fn hint_button_system(
+    mut interaction_query: HintButtonQuery,
+    mut game_progress: ResMut<GameProgress>,
+    analysis: Res<BoardAnalysis>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                // 解があり、まだ段階が残っている場合のみヒントを使う
+                if analysis.first_step().is_none() {
+                    debug!("No hint available for this board.");
+                } else if let Some(level) = game_progress.use_hint() {
+                    info!(
+                        "Hint {}/{} used (-{} points)",
+                        level, MAX_HINT_LEVEL, HINT_COST
+                    );
+                } else {
+                    debug!("All hints have been used.");
+                }
+
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered => {
+                *color = Color::srgb(0.6, 0.6, 0.3).into();
+            }
+            Interaction::None => {
+                *color = Color::srgb(0.5, 0.5, 0.2).into();
+            }
+        }
+    }
+}
+
+// ヒント表示システム - 現在のステージで使ったヒントの段階に応じて表示
+pub fn hint_display_system(
+    game_progress: Res<GameProgress>,
+    analysis: Res<BoardAnalysis>,
+    mut hint_query: Query<&mut Text, With<HintDisplay>>,
+) {
+    if game_progress.is_changed() || analysis.is_changed() {
+        let level = game_progress.hints_used();
+        let hint = if level == 0 {
+            String::new()
+        } else {
+            analysis
+                .first_step()
+                .map(|step| {
+                    format!(
+                        "Hint {}/{}: {}",
+                        level,
+                        MAX_HINT_LEVEL,
+                        step.hint_text(level)
+                    )
+                })
+                .unwrap_or_default()
+        };
+
+        for mut text in hint_query.iter_mut() {
+            **text = hint.clone();
+        }
+    }
+}
+
+// デイリー状況表示システム - 進行状況と今日の記録を表示
+pub fn daily_status_display_system(
+    daily: Res<DailyChallenge>,
+    daily_records: Res<DailyRecords>,
+    mut status_query: Query<&mut Text, With<DailyStatusDisplay>>,
+) {
+    if daily.is_changed() || daily_records.is_changed() {
+        let status = match daily.date {
+            Some(date) => format!("Daily {}: {}/{}", date, daily.index + 1, DAILY_PUZZLE_COUNT),
+            None => {
+                let today = DailyDate::today();
+                match daily_records.time_for(today) {
+                    Some(time) => format!("Daily {}: cleared in {:.1}s", today, time),
+                    None => format!("Daily {}: not cleared", today),
+                }
+            }
+        };
+
+        for mut text in status_query.iter_mut() {
+            **text = status.clone();
+        }
+    }
+}
+
+// パズルコード表示システム - 盤面やルールが変わったときにコードとタイトルを更新
+pub fn puzzle_code_display_system(
+    game_numbers: Res<GameNumbers>,
+    rules: Res<RuleSet>,
+    mut code_query: Query<&mut Text, (With<PuzzleCodeDisplay>, Without<TitleDisplay>)>,
+    mut title_query: Query<&mut Text, (With<TitleDisplay>, Without<PuzzleCodeDisplay>)>,
+) {
+    if game_numbers.is_changed() || rules.is_changed() {
+        let code = game_numbers.to_code(&rules).unwrap_or_default();
+        for mut text in code_query.iter_mut() {
+            **text = format!("Code: {}", code);
+        }
+
+        for mut text in title_query.iter_mut() {
+            **text = format!("{} Game", rules.title());
+        }
+    }
+}
+
+// パズルコード入力システム - 入力欄の操作とキーボード入力、コードの読み込み
+#[allow(clippy::too_many_arguments)]
+pub fn code_input_system(
+    mut interaction_query: CodeInputQuery,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut input: ResMut<CodeInputState>,
+    mut game_numbers: ResMut<GameNumbers>,
+    mut rules: ResMut<RuleSet>,
+    mut calc_state: ResMut<CalculationState>,
+    mut daily: ResMut<DailyChallenge>,
+    mut game_progress: ResMut<GameProgress>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut text_query: Query<&mut Text, With<CodeInputText>>,
+) {
+    let mut submit = false;
+
+    for (interaction, mut color, field) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                if field.is_some() {
+                    // 入力欄をクリックすると入力開始
+                    input.focused = true;
+                    input.message = None;
+                } else {
+                    submit = true;
+                }
+                *color = Color::srgb(0.8, 0.8, 0.8).into();
+            }
+            Interaction::Hovered | Interaction::None => {
+                *color = match (field.is_some(), input.focused) {
+                    (true, true) => CODE_INPUT_FOCUSED_COLOR,
+                    (true, false) => CODE_INPUT_COLOR,
+                    (false, _) if *interaction == Interaction::Hovered => {
+                        Color::srgb(0.4, 0.5, 0.7)
+                    }
+                    (false, _) => Color::srgb(0.3, 0.4, 0.6),
+                }
+                .into();
+            }
+        }
+    }
+
+    // 入力中のみキーボード入力を受け付ける
+    for event in keyboard_events.read() {
+        if !input.focused || event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(chars) => {
+                for c in chars
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+                {
+                    input.push(c);
+                }
+            }
+            Key::Backspace => {
+                input.text.pop();
+            }
+            Key::Enter => submit = true,
+            Key::Escape => input.focused = false,
+            _ => {}
+        }
+    }
+
+    if submit {
+        match GameNumbers::from_code(&input.text) {
+            Ok((numbers, code_rules)) => {
+                // コードの盤面で通常モードとして開始
+                daily.date = None;
+                *game_numbers = numbers;
+                *rules = code_rules;
+                calc_state.reset(&game_numbers);
+                // 解きかけの盤面はギブアップ扱い（ライフがなくなった場合はゲームオーバー）
+                if game_progress.replace_board() {
+                    next_state.set(GameState::GameOver);
+                }
+                input.text.clear();
+                input.focused = false;
+                input.message = None;
+
+                info!("Loaded puzzle code: {:?}", game_numbers.digits);
+            }
+            Err(error) => {
+                input.message = Some(format!("Invalid code: {}", error));
+                debug!("Invalid puzzle code: {}", error);
+            }
+        }
+    }
+
+    if input.is_changed() {
+        for mut text in text_query.iter_mut() {
+            **text = input.display_text();
+        }
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use crate::game::TimeAttack;
+    use crate::game::state::GameState;
+    use crate::ui::systems::time_attack_clock_system;
+    use bevy::prelude::*;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    // 1回の更新で進む時間
+    const STEP: Duration = Duration::from_millis(200);
+
+    // 手動で時間を進めるアプリを作成（最初の更新では時間が進まない）
+    fn time_attack_app(remaining: Duration) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(STEP))
+            .insert_resource(TimeAttack {
+                active: true,
+                remaining,
+                ..default()
+            })
+            .init_state::<GameState>()
+            .add_systems(
+                Update,
+                time_attack_clock_system.run_if(in_state(GameState::Playing)),
+            );
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+        app
+    }
+
+    fn state(app: &App) -> GameState {
+        *app.world().resource::<State<GameState>>().get()
+    }
+
+    // 時間の経過でカウントダウンが進むテスト
+    #[test]
+    fn test_clock_counts_down_with_time() {
+        let mut app = time_attack_app(Duration::from_secs(10));
+        let start = app.world().resource::<TimeAttack>().remaining;
+
+        for _ in 0..5 {
+            app.update();
+        }
+        let remaining = app.world().resource::<TimeAttack>().remaining;
+        assert_eq!(start - remaining, STEP * 5);
+        assert_eq!(state(&app), GameState::Playing);
+    }
+
+    // 時間切れで結果画面の状態に移るテスト
+    #[test]
+    fn test_time_up_shows_results() {
+        let mut app = time_attack_app(STEP * 3);
+
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(app.world().resource::<TimeAttack>().is_time_up());
+
+        // 状態は次の更新の最初に切り替わる
+        app.update();
+        assert_eq!(state(&app), GameState::TimeUp);
+    }
+
+    // 一時停止中は時間が減らないテスト
+    #[test]
+    fn test_clock_stops_while_paused() {
+        let mut app = time_attack_app(Duration::from_secs(10));
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Paused);
+        app.update();
+        let paused_at = app.world().resource::<TimeAttack>().remaining;
+
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<TimeAttack>().remaining, paused_at);
+        assert_eq!(state(&app), GameState::Paused);
+    }
+}